@@ -0,0 +1,81 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use image::{ImageBuffer, Rgb, RgbImage};
+
+use pixel_sort::sorting;
+use pixel_sort::PixelSorter;
+
+fn test_image(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        Rgb([
+            (x * 37 + y * 11) as u8,
+            (x * 7 + y * 53) as u8,
+            (x ^ y) as u8,
+        ])
+    })
+}
+
+/// Compares sorting with a comparator against sorting with precomputed keys
+fn interval_sort(c: &mut Criterion) {
+    let image = test_image(4096, 1);
+    let pixels: Vec<Rgb<u8>> = image.pixels().copied().collect();
+
+    let methods = [
+        ("hue", sorting::SortMethod::Hue),
+        ("brightness", sorting::SortMethod::Brightness),
+        ("red", sorting::SortMethod::RgbRed),
+    ];
+
+    let mut group = c.benchmark_group("interval_sort");
+
+    for (name, method) in methods.iter() {
+        group.bench_with_input(BenchmarkId::new("comparator", name), method, |b, m| {
+            b.iter(|| {
+                let mut interval = pixels.clone();
+                interval.sort_by(sorting::compare(m));
+                black_box(interval)
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("keyed", name), method, |b, m| {
+            b.iter(|| {
+                let mut interval = pixels.clone();
+                sorting::sort_pixels(&mut interval, m, false);
+                black_box(interval)
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn full_image_sort(c: &mut Criterion) {
+    let image = test_image(1024, 256);
+    let image_16: ImageBuffer<Rgb<u16>, Vec<u16>> =
+        image::DynamicImage::from(image.clone()).into_rgb16();
+    let intervals = pixel_sort::interval::entire_row(&image);
+    let sorter = PixelSorter::new().sort_method(sorting::SortMethod::Hue);
+
+    let mut group = c.benchmark_group("full_image_sort");
+    group.sample_size(20);
+
+    group.bench_function("hue_rgb8", |b| {
+        b.iter(|| {
+            black_box(pixel_sort::get_sorted_image_raw(
+                &image, None, &intervals, &sorter,
+            ))
+        })
+    });
+
+    group.bench_function("hue_rgb16", |b| {
+        b.iter(|| {
+            black_box(pixel_sort::get_sorted_image_raw(
+                &image_16, None, &intervals, &sorter,
+            ))
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, interval_sort, full_image_sort);
+criterion_main!(benches);
@@ -1,9 +1,17 @@
 use image::DynamicImage;
 use image::GenericImageView;
+use image::ImageBuffer;
+use image::Pixel;
+use image::Rgb;
 use std::path::Path;
 
+use pixel_sort::color::luma;
 use pixel_sort::interval;
 use pixel_sort::sorting;
+use pixel_sort::Direction;
+use pixel_sort::Mask;
+use pixel_sort::MaskMode;
+use pixel_sort::PixelSorter;
 
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -51,6 +59,29 @@ struct Opt {
     )]
     threshold_inclusive: bool,
 
+    #[structopt(
+        short = "T",
+        long = "threshold-measure",
+        default_value = "light",
+        help = "What the thresholds are compared against, takes the same names as --sort"
+    )]
+    threshold_measure: String,
+
+    #[structopt(
+        long = "luma",
+        default_value = "709",
+        help = "Coefficients used by luma sorting and thresholds: [601 709 2020]"
+    )]
+    luma_standard: String,
+
+    #[structopt(
+        short = "E",
+        long = "edge-threshold",
+        default_value = "0.3",
+        help = "How strong an edge must be to break an interval for edge intervals (a hard step is 1)"
+    )]
+    edge_threshold: f32,
+
     #[structopt(
         short = "a",
         long = "angle",
@@ -70,7 +101,7 @@ struct Opt {
         short = "m",
         long = "interval",
         default_value = "random",
-        help = "Interval generation mode: [rand thresh full zig angle]"
+        help = "Interval generation mode: [rand thresh span full edge zig angle]"
     )]
     interval_method: String,
 
@@ -78,10 +109,48 @@ struct Opt {
         short = "s",
         long = "sort",
         default_value = "brightness",
-        help = "Pixel comparison mode: [hue hsbsat hslsat light bright intensity min red green blue]"
+        help = "Pixel comparison mode: [hue hsbsat hslsat light bright intensity min red green blue alpha cyan magenta yellow key lab-light lch-chroma lch-hue oklab-light oklch-chroma oklch-hue luma]"
     )]
     sort_method: String,
 
+    #[structopt(
+        short = "A",
+        long = "alpha",
+        default_value = "sort",
+        help = "How fully transparent pixels are treated: [sort break exclude]"
+    )]
+    alpha_mode: String,
+
+    #[structopt(
+        short = "O",
+        long = "order",
+        default_value = "asc",
+        help = "Sort order: [asc desc alt-interval alt-row]"
+    )]
+    order: String,
+
+    #[structopt(
+        short = "R",
+        long = "reverse",
+        help = "Sort descending, same as --order desc"
+    )]
+    reverse: bool,
+
+    #[structopt(
+        short = "t",
+        long = "threads",
+        default_value = "0",
+        help = "How many threads to sort with (0 uses every core)"
+    )]
+    threads: usize,
+
+    #[structopt(
+        short = "S",
+        long = "seed",
+        help = "Seed for the random intervals and skips, a random one is picked and printed if not given"
+    )]
+    seed: Option<u64>,
+
     /// Input file
     #[structopt(short, long, parse(from_os_str))]
     input: PathBuf,
@@ -89,11 +158,223 @@ struct Opt {
     #[structopt(short = "M", long = "mask", parse(from_os_str), default_value = "")]
     input_mask: PathBuf,
 
+    #[structopt(
+        long = "mask-threshold",
+        help = "Brightness of the mask in 0.0-1.0 above which pixels are sorted [default: 128/255]"
+    )]
+    mask_threshold: Option<f32>,
+
+    #[structopt(
+        long = "soft-mask",
+        help = "Blend sorted and original pixels by the mask brightness instead of a threshold"
+    )]
+    soft_mask: bool,
+
+    #[structopt(
+        long = "invert-mask",
+        help = "Sort where the mask is dark instead of bright"
+    )]
+    invert_mask: bool,
+
+    #[structopt(
+        long = "auto-mask",
+        default_value = "",
+        help = "Build the mask from the image itself: [luma key thresh]"
+    )]
+    auto_mask: String,
+
+    #[structopt(
+        long = "mask-lower",
+        default_value = "0.0",
+        help = "Lowest luma the luma auto mask lets through"
+    )]
+    mask_lower: f32,
+
+    #[structopt(
+        long = "mask-upper",
+        default_value = "1.0",
+        help = "Highest luma the luma auto mask lets through"
+    )]
+    mask_upper: f32,
+
+    #[structopt(
+        long = "key-color",
+        help = "Color the key auto mask picks out as hex, like 87ceeb"
+    )]
+    key_color: Option<String>,
+
+    #[structopt(
+        long = "key-hue",
+        default_value = "20",
+        help = "How many degrees the hue may be off from the key color"
+    )]
+    key_hue_tolerance: f32,
+
+    #[structopt(
+        long = "key-chroma",
+        default_value = "0.05",
+        help = "How far the OKLCH chroma may be off from the key color"
+    )]
+    key_chroma_tolerance: f32,
+
+    #[structopt(
+        long = "erode",
+        default_value = "0",
+        help = "Shrink the mask by this many pixels, done before growing it"
+    )]
+    erode: u32,
+
+    #[structopt(
+        long = "dilate",
+        default_value = "0",
+        help = "Grow the mask by this many pixels"
+    )]
+    dilate: u32,
+
+    #[structopt(
+        long = "mask-mode",
+        default_value = "restore",
+        help = "How masked out pixels are treated: [restore split]"
+    )]
+    mask_mode: String,
+
     /// Output file
     #[structopt(short, long, parse(from_os_str))]
     output: PathBuf,
 }
 
+fn sort_buffer<P>(
+    buffer: &ImageBuffer<P, Vec<P::Subpixel>>,
+    sorter: &PixelSorter,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + Send + Sync + 'static,
+    P::Subpixel: Send + Sync,
+{
+    match sorter.apply(buffer) {
+        Ok(sorted) => sorted,
+        Err(e) => {
+            println!("There was an error sorting the image: {}", e);
+            std::process::exit(1)
+        }
+    }
+}
+
+fn print_progress(done: u32, total: u32) {
+    let progress = done as u64 * 100 / total as u64;
+    let last_progress = (done as u64 - 1) * 100 / total as u64;
+
+    if progress / 10 != last_progress / 10 {
+        println!("Sort progress: {}%", progress / 10 * 10);
+    }
+}
+
+/// Finds the sort method with the given name, used by both --sort and --threshold-measure
+fn parse_sort_method(name: &str, luma_standard: luma::LumaStandard) -> Option<sorting::SortMethod> {
+    match name.to_lowercase().as_str() {
+        "hue" => Some(sorting::SortMethod::Hue),
+        "hsbsat" | "hsbsaturation" => Some(sorting::SortMethod::HsbSaturation),
+        "hslsat" | "hslsaturation" => Some(sorting::SortMethod::HslSaturation),
+        "light" | "lightness" => Some(sorting::SortMethod::Lightness),
+        "bright" | "brightness" => Some(sorting::SortMethod::Brightness),
+        "intensity" => Some(sorting::SortMethod::Intensity),
+        "min" | "minimum" => Some(sorting::SortMethod::Minimum),
+        "red" | "r" => Some(sorting::SortMethod::RgbRed),
+        "green" | "g" => Some(sorting::SortMethod::RgbGreen),
+        "blue" | "b" => Some(sorting::SortMethod::RgbBlue),
+        "alpha" | "a" => Some(sorting::SortMethod::Alpha),
+        "cyan" | "c" => Some(sorting::SortMethod::CmykCyan),
+        "magenta" | "m" => Some(sorting::SortMethod::CmykMagenta),
+        "yellow" | "y" => Some(sorting::SortMethod::CmykYellow),
+        "key" | "black" | "k" => Some(sorting::SortMethod::CmykKey),
+        "lab-light" | "lab-lightness" => Some(sorting::SortMethod::LabLightness),
+        "lch-chroma" | "lab-chroma" => Some(sorting::SortMethod::LabChroma),
+        "lch-hue" | "lab-hue" => Some(sorting::SortMethod::LabHue),
+        "oklab-light" | "oklab-lightness" => Some(sorting::SortMethod::OklabLightness),
+        "oklch-chroma" | "oklab-chroma" => Some(sorting::SortMethod::OklabChroma),
+        "oklch-hue" | "oklab-hue" => Some(sorting::SortMethod::OklabHue),
+        "luma" => Some(sorting::SortMethod::Luma(luma_standard)),
+        _ => None,
+    }
+}
+
+/// Saves in the type the image was sorted in, falling back to 8 bit channels
+/// for formats that cannot store it, like 16 bit JPEG or float PNG
+fn save_image(img: &DynamicImage, path: &Path) -> image::ImageResult<()> {
+    // encoders turn down pixel types either as unsupported or as an encoding error
+    let rejected = |result: &image::ImageResult<()>| {
+        matches!(
+            result,
+            Err(image::ImageError::Unsupported(_)) | Err(image::ImageError::Encoding(_))
+        )
+    };
+
+    let mut result = img.save(path);
+
+    if rejected(&result) && img.color().has_alpha() {
+        result = img.to_rgba8().save(path);
+    }
+
+    // the format may not store alpha either, like JPEG
+    if rejected(&result) {
+        result = img.to_rgb8().save(path);
+    }
+
+    result
+}
+
+/// Reads a color written as hex like 87ceeb, with or without a leading #
+fn parse_hex_color(text: &str) -> Option<Rgb<u8>> {
+    let hex = text.trim_start_matches('#');
+
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    Some(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+/// Builds the mask chosen with --auto-mask from the image, `None` when no auto mask is wanted
+fn auto_mask(opt: &Opt, img: &DynamicImage, luma_standard: luma::LumaStandard) -> Option<Mask> {
+    let mask = match opt.auto_mask.to_lowercase().as_str() {
+        "" | "none" => return None,
+        "luma" | "luminance" => Mask::from_range(
+            img,
+            &sorting::SortMethod::Luma(luma_standard),
+            opt.mask_lower,
+            opt.mask_upper,
+        ),
+        "key" | "color" => {
+            let key_color = opt.key_color.as_deref().and_then(parse_hex_color);
+
+            let Some(key_color) = key_color else {
+                println!("The key auto mask needs a --key-color written as hex like 87ceeb");
+                std::process::exit(1)
+            };
+
+            Mask::from_color_key(
+                img,
+                key_color,
+                opt.key_hue_tolerance,
+                opt.key_chroma_tolerance,
+            )
+        }
+        "thresh" | "threshold" => Mask::from_threshold(
+            img,
+            opt.mask_threshold.unwrap_or(Mask::DEFAULT_THRESHOLD),
+            false,
+        ),
+        _ => {
+            println!("Unsure what mask to build, sorting without a mask");
+            return None;
+        }
+    };
+
+    Some(if opt.invert_mask { mask.invert() } else { mask })
+}
+
 fn main() {
     let opt = Opt::from_args();
 
@@ -105,37 +386,120 @@ fn main() {
         std::process::exit(1)
     }
 
-    let sort_method = match opt.sort_method.to_lowercase().as_str() {
-        "hue" => sorting::SortMethod::Hue,
-        "hsbsat" | "hsbsaturation" => sorting::SortMethod::HsbSaturation,
-        "hslsat" | "hslsaturation" => sorting::SortMethod::HslSaturation,
-        "light" | "lightness" => sorting::SortMethod::Lightness,
-        "bright" | "brightness" => sorting::SortMethod::Brightness,
-        "intensity" => sorting::SortMethod::Intensity,
-        "min" | "minimum" => sorting::SortMethod::Minimum,
-        "red" | "r" => sorting::SortMethod::RgbRed,
-        "green" | "g" => sorting::SortMethod::RgbGreen,
-        "blue" | "b" => sorting::SortMethod::RgbBlue,
+    #[cfg(not(feature = "parallel"))]
+    if opt.threads > 1 {
+        println!("Built without the parallel feature, sorting on a single thread");
+    }
+
+    let seed = opt.seed.unwrap_or_else(rand::random);
+
+    println!("Using seed {}", seed);
+
+    let luma_standard = match opt.luma_standard.to_lowercase().as_str() {
+        "601" | "rec601" => luma::LumaStandard::Rec601,
+        "709" | "rec709" => luma::LumaStandard::Rec709,
+        "2020" | "rec2020" => luma::LumaStandard::Rec2020,
         _ => {
-            println!("Unsure what sorting method to use, defaulting to brightness");
-            sorting::SortMethod::Brightness
+            println!("Unsure what luma coefficients to use, defaulting to Rec.709");
+            luma::LumaStandard::Rec709
         }
     };
 
-    let interval_by = match opt.interval_method.to_lowercase().as_str() {
-        "rand" | "random" => interval::Interval::Random,
-        "thresh" | "threshold" => interval::Interval::Threshold,
-        "entire" | "row" | "full" => interval::Interval::EntireRow,
-        "zig" | "zigzag" => interval::Interval::AbsSinWave,
-        "angle" | "deg" => interval::Interval::Angle,
+    let sort_method = parse_sort_method(&opt.sort_method, luma_standard).unwrap_or_else(|| {
+        println!("Unsure what sorting method to use, defaulting to brightness");
+        sorting::SortMethod::Brightness
+    });
+
+    let alpha_mode = match opt.alpha_mode.to_lowercase().as_str() {
+        "sort" => sorting::AlphaMode::Sort,
+        "break" | "split" => sorting::AlphaMode::Break,
+        "exclude" | "skip" => sorting::AlphaMode::Exclude,
+        _ => {
+            println!("Unsure how to treat transparent pixels, defaulting to sort");
+            sorting::AlphaMode::Sort
+        }
+    };
+
+    let order = match opt.order.to_lowercase().as_str() {
+        _ if opt.reverse => sorting::SortOrder::Descending,
+        "asc" | "ascending" => sorting::SortOrder::Ascending,
+        "desc" | "descending" => sorting::SortOrder::Descending,
+        "alt-interval" | "alternate-interval" => sorting::SortOrder::AlternatingInterval,
+        "alt-row" | "alternate-row" => sorting::SortOrder::AlternatingRow,
+        _ => {
+            println!("Unsure what sort order to use, defaulting to ascending");
+            sorting::SortOrder::Ascending
+        }
+    };
+
+    let mask_mode = match opt.mask_mode.to_lowercase().as_str() {
+        "restore" => MaskMode::Restore,
+        "split" => MaskMode::Split,
+        _ => {
+            println!("Unsure how to treat masked out pixels, defaulting to restore");
+            MaskMode::Restore
+        }
+    };
+
+    let threshold_measure = parse_sort_method(&opt.threshold_measure, luma_standard)
+        .unwrap_or_else(|| {
+            println!("Unsure what to measure the thresholds against, defaulting to lightness");
+            sorting::SortMethod::Lightness
+        });
+
+    let random = interval::Random {
+        length: opt.interval_length,
+    };
+
+    let sorter = PixelSorter::new();
+
+    let sorter = match opt.interval_method.to_lowercase().as_str() {
+        "rand" | "random" => sorter.interval(random),
+        "thresh" | "threshold" => sorter.interval(interval::Threshold {
+            lower: opt.lower_threshold,
+            upper: opt.upper_threshold,
+            inclusive: opt.threshold_inclusive,
+            measure: threshold_measure,
+            mode: interval::ThresholdMode::Cut,
+        }),
+        "span" | "thresh-span" => sorter.interval(interval::Threshold {
+            lower: opt.lower_threshold,
+            upper: opt.upper_threshold,
+            inclusive: opt.threshold_inclusive,
+            measure: threshold_measure,
+            mode: interval::ThresholdMode::Span,
+        }),
+        "entire" | "row" | "full" => sorter.interval(interval::EntireRow),
+        "edge" | "edges" | "sobel" => sorter.interval(interval::Edges {
+            threshold: opt.edge_threshold,
+        }),
+        "zig" | "zigzag" => sorter.interval(interval::SinWave {
+            length: opt.interval_length,
+        }),
+        "angle" | "deg" => sorter.interval(interval::Angle { angle: opt.angle }),
         _ => {
             println!("Unsure what interval grouping to use, defaulting to random");
-            interval::Interval::Random
+            sorter.interval(random)
         }
     };
 
+    let mut sorter = sorter
+        .sort_method(sort_method)
+        .alpha_mode(alpha_mode)
+        .order(order)
+        .mask_mode(mask_mode)
+        .randomness(opt.randomness)
+        .direction(if opt.vertical {
+            Direction::Vertical
+        } else {
+            Direction::Horizontal
+        })
+        .seed(seed)
+        .threads(opt.threads)
+        .progress(print_progress);
+
     let mut img;
-    match image::open(opt.input) {
+    match image::open(&opt.input) {
         Ok(image) => img = image,
         Err(e) => {
             println!("There was an error reading the image: {}", e);
@@ -146,7 +510,7 @@ fn main() {
     let data_mask = if !opt.input_mask.is_file() {
         Option::None
     } else {
-        match image::open(opt.input_mask) {
+        match image::open(&opt.input_mask) {
             Ok(image) => Option::Some(image),
             Err(e) => {
                 println!("There was an error reading the mask: {}", e);
@@ -164,8 +528,7 @@ fn main() {
         img.color()
     );
 
-    let mask_data;
-    let data_mask = if let Some(d) = data_mask {
+    let mask = if let Some(d) = data_mask {
         let (m_width, m_height) = d.dimensions();
 
         println!(
@@ -174,56 +537,66 @@ fn main() {
             m_height,
             d.color()
         );
-        if m_width != width || m_height != height {
-            println!("The mask image must have the same size as the input image!");
-            std::process::exit(1);
+
+        if !opt.auto_mask.is_empty() {
+            println!("Use either --mask or --auto-mask, not both");
+            std::process::exit(1)
         }
 
-        mask_data = pixel_sort::math::to_binary_mask(if opt.vertical { d.rotate90() } else { d });
-        Option::Some(&mask_data)
+        Some(if opt.soft_mask {
+            Mask::from_image(&d, opt.invert_mask)
+        } else {
+            let threshold = opt.mask_threshold.unwrap_or(Mask::DEFAULT_THRESHOLD);
+            Mask::from_threshold(&d, threshold, opt.invert_mask)
+        })
     } else {
-        Option::None
+        auto_mask(&opt, &img, luma_standard)
     };
 
-    if opt.vertical {
-        img = img.rotate90();
-    }
-
-    let buffer = img.into_rgb8();
+    if let Some(mut mask) = mask {
+        if opt.erode > 0 {
+            mask = mask.erode(opt.erode);
+        }
+        if opt.dilate > 0 {
+            mask = mask.dilate(opt.dilate);
+        }
 
-    let intervals = interval::get_interval(
-        &interval_by,
-        &buffer,
-        &opt.interval_length,
-        &opt.lower_threshold,
-        &opt.upper_threshold,
-        &opt.angle,
-        &opt.threshold_inclusive,
-    );
+        sorter = sorter.mask(mask);
+    }
 
-    println!("Intervals found!");
     println!("Starting sorting...");
 
-    img = {
-        DynamicImage::from(pixel_sort::get_sorted_image(
-            &buffer,
-            data_mask,
-            &intervals,
-            opt.randomness,
-            &sort_method,
-        ))
-    };
-
-    if opt.vertical {
-        img = img.rotate270();
+    // sort in the image's own pixel type so no precision or alpha is lost
+    macro_rules! sort_dynamic_image {
+        ($($variant:ident),*) => {
+            match img {
+                $(DynamicImage::$variant(buffer) => {
+                    DynamicImage::from(sort_buffer(&buffer, &sorter))
+                })*
+                other => DynamicImage::from(sort_buffer(&other.into_rgba32f(), &sorter)),
+            }
+        };
     }
 
+    img = sort_dynamic_image!(
+        ImageLuma8,
+        ImageLumaA8,
+        ImageRgb8,
+        ImageRgba8,
+        ImageLuma16,
+        ImageLumaA16,
+        ImageRgb16,
+        ImageRgba16,
+        ImageRgb32F,
+        ImageRgba32F
+    );
+
     println!("Sorting done!");
     println!("Saving image...");
 
-    match img.save(&opt.output) {
-        Ok(_) => (),
-        Err(e) => println!("{}", e),
+    if let Err(e) = save_image(&img, &opt.output) {
+        println!("There was an error saving the image: {}", e);
+        std::process::exit(1)
     }
     println!("Image saved to {}", opt.output.to_string_lossy());
 }
@@ -1,5 +1,50 @@
-
+use image::Pixel;
+use image::Primitive;
+use num_traits::ToPrimitive;
 
 pub mod hsb;
 pub mod hsl;
-pub mod cmyk;
\ No newline at end of file
+pub mod cmyk;
+pub mod lab;
+pub mod luma;
+pub mod oklab;
+
+/// Below this chroma a color counts as gray and gets a hue of 0,
+/// otherwise rounding noise would scatter grays over every hue
+pub(crate) const GRAY_CHROMA: f32 = 1e-4;
+
+/// Converts any pixel into red, green, blue and alpha channels normalized to 0.0-1.0
+///
+/// The `rgb_f32_get_*` functions of the color modules take channels in this range,
+/// their `rgb_get_*` twins take 8 bit channels
+pub fn to_rgba_f32<P: Pixel>(pixel: &P) -> [f32; 4] {
+    let max = P::Subpixel::DEFAULT_MAX_VALUE.to_f32().unwrap_or(1_f32);
+    let rgba = pixel.to_rgba();
+
+    [
+        rgba[0].to_f32().unwrap_or(0_f32) / max,
+        rgba[1].to_f32().unwrap_or(0_f32) / max,
+        rgba[2].to_f32().unwrap_or(0_f32) / max,
+        rgba[3].to_f32().unwrap_or(0_f32) / max,
+    ]
+}
+
+/// Undoes the sRGB transfer curve of a channel normalized to 0.0-1.0
+pub fn srgb_to_linear(channel: &f32) -> f32 {
+    if *channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Turns the a and b axes of a Lab like space into chroma and a hue angle in degrees 0-360
+pub(crate) fn chroma_hue(a: &f32, b: &f32) -> (f32, f32) {
+    let chroma = a.hypot(*b);
+
+    if chroma < GRAY_CHROMA {
+        return (chroma, 0_f32);
+    }
+
+    (chroma, b.atan2(*a).to_degrees().rem_euclid(360_f32))
+}
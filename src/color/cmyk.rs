@@ -1,3 +1,5 @@
+//! CMYK inks of sRGB colors, without any color profile
+
 pub struct CMYK {
    pub c: f32,
    pub m: f32,
@@ -48,3 +50,59 @@ impl CMYK {
         CMYK::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
     }
 }
+
+pub fn rgb_get_cyan(r: &u8, g: &u8, b: &u8) -> f32 {
+    rgb_f32_get_cyan(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_cyan(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    calculate_ink(f_r, &rgb_f32_get_key(f_r, f_g, f_b))
+}
+
+pub fn rgb_get_magenta(r: &u8, g: &u8, b: &u8) -> f32 {
+    rgb_f32_get_magenta(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_magenta(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    calculate_ink(f_g, &rgb_f32_get_key(f_r, f_g, f_b))
+}
+
+pub fn rgb_get_yellow(r: &u8, g: &u8, b: &u8) -> f32 {
+    rgb_f32_get_yellow(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_yellow(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    calculate_ink(f_b, &rgb_f32_get_key(f_r, f_g, f_b))
+}
+
+pub fn rgb_get_key(r: &u8, g: &u8, b: &u8) -> f32 {
+    rgb_f32_get_key(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_key(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    1_f32 - f_r.max(*f_g).max(*f_b)
+}
+
+/// The cyan, magenta or yellow part of the channel opposite to it, pure black has none
+fn calculate_ink(f_channel: &f32, key: &f32) -> f32 {
+    if *key >= 1_f32 {
+        return 0_f32;
+    }
+    (1_f32 - f_channel - key) / (1_f32 - key)
+}
@@ -1,4 +1,4 @@
-
+//! Hue, saturation and brightness of sRGB colors
 
 pub struct HSB {
     pub hue: f32,
@@ -68,7 +68,7 @@ fn calculate_hue(f_r: &f32, f_g: &f32, f_b: &f32, max: &f32, min: &f32) -> f32 {
         if hue < 0_f32 {
             return hue + 1_f32;
         }
-        return hue;
+        hue
     } else if max == f_b {
         (4_f32 + (f_r - f_g) / (f_b - min)) / 6_f32
     } else {
@@ -77,9 +77,15 @@ fn calculate_hue(f_r: &f32, f_g: &f32, f_b: &f32, max: &f32, min: &f32) -> f32 {
 }
 
 pub fn rgb_get_hue(r: &u8, g: &u8, b: &u8) -> f32 {
-    let f_r = (*r as f32) / 255_f32;
-    let f_g = (*g as f32) / 255_f32;
-    let f_b = (*b as f32) / 255_f32;
+    rgb_f32_get_hue(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_hue(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    let (f_r, f_g, f_b) = (*f_r, *f_g, *f_b);
 
     let mut min = f_r;
     let mut max = f_r;
@@ -102,9 +108,15 @@ pub fn rgb_get_hue(r: &u8, g: &u8, b: &u8) -> f32 {
 }
 
 pub fn rgb_get_saturation(r: &u8, g: &u8, b: &u8) -> f32 {
-    let f_r = (*r as f32) / 255_f32;
-    let f_g = (*g as f32) / 255_f32;
-    let f_b = (*b as f32) / 255_f32;
+    rgb_f32_get_saturation(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_saturation(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    let (f_r, f_g, f_b) = (*f_r, *f_g, *f_b);
 
     let mut min = f_r;
     let mut max = f_r;
@@ -126,7 +138,7 @@ pub fn rgb_get_saturation(r: &u8, g: &u8, b: &u8) -> f32 {
         return (max - min) / max;
     }
 
-    return 0f32;
+    0f32
 }
 
 pub fn rgb_get_brightness(r: &u8, g: &u8, b: &u8) -> f32 {
@@ -139,5 +151,9 @@ pub fn rgb_get_brightness(r: &u8, g: &u8, b: &u8) -> f32 {
     if b > max {
         max = b;
     }
-    return (*max as f32) / 255_f32;
+    (*max as f32) / 255_f32
+}
+
+pub fn rgb_f32_get_brightness(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    f_r.max(*f_g).max(*f_b)
 }
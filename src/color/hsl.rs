@@ -1,3 +1,5 @@
+//! Hue, saturation and lightness of sRGB colors
+
 pub struct HSL {
     pub hue: f32,
     pub saturation: f32,
@@ -66,7 +68,7 @@ fn calculate_hue(f_r: &f32, f_g: &f32, f_b: &f32, max: &f32, min: &f32) -> f32 {
         if hue < 0_f32 {
             return hue + 1_f32;
         }
-        return hue;
+        hue
     } else if max == f_b {
         (4_f32 + (f_r - f_g) / (f_b - min)) / 6_f32
     } else {
@@ -75,9 +77,15 @@ fn calculate_hue(f_r: &f32, f_g: &f32, f_b: &f32, max: &f32, min: &f32) -> f32 {
 }
 
 pub fn rgb_get_hue(r: &u8, g: &u8, b: &u8) -> f32 {
-    let f_r = (*r as f32) / 255_f32;
-    let f_g = (*g as f32) / 255_f32;
-    let f_b = (*b as f32) / 255_f32;
+    rgb_f32_get_hue(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_hue(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    let (f_r, f_g, f_b) = (*f_r, *f_g, *f_b);
 
     let mut min = f_r;
     let mut max = f_r;
@@ -100,9 +108,15 @@ pub fn rgb_get_hue(r: &u8, g: &u8, b: &u8) -> f32 {
 }
 
 pub fn rgb_get_saturation(r: &u8, g: &u8, b: &u8) -> f32 {
-    let f_r = (*r as f32) / 255_f32;
-    let f_g = (*g as f32) / 255_f32;
-    let f_b = (*b as f32) / 255_f32;
+    rgb_f32_get_saturation(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_saturation(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    let (f_r, f_g, f_b) = (*f_r, *f_g, *f_b);
 
     let mut min = f_r;
     let mut max = f_r;
@@ -130,13 +144,19 @@ pub fn rgb_get_saturation(r: &u8, g: &u8, b: &u8) -> f32 {
         return (max - min) / (1_f32 - (2_f32 * lightness - 1_f32).abs());
     }
 
-    return 0f32;
+    0f32
 }
 
 pub fn rgb_get_lightness(r: &u8, g: &u8, b: &u8) -> f32 {
-    let f_r = (*r as f32) / 255_f32;
-    let f_g = (*g as f32) / 255_f32;
-    let f_b = (*b as f32) / 255_f32;
+    rgb_f32_get_lightness(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+    )
+}
+
+pub fn rgb_f32_get_lightness(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    let (f_r, f_g, f_b) = (*f_r, *f_g, *f_b);
 
     let mut min = f_r;
     let mut max = f_r;
@@ -154,6 +174,5 @@ pub fn rgb_get_lightness(r: &u8, g: &u8, b: &u8) -> f32 {
     if f_b > max {
         max = f_b;
     }
-    let lightness = (max + min) / 2_f32;
-    lightness
+    (max + min) / 2_f32
 }
\ No newline at end of file
@@ -0,0 +1,114 @@
+//! CIELAB and its polar form LCH, relative to the D65 white point
+
+use crate::color;
+
+/// D65 reference white in XYZ
+const WHITE_X: f32 = 0.95047;
+const WHITE_Y: f32 = 1_f32;
+const WHITE_Z: f32 = 1.08883;
+
+/// CIE L*a*b* with a D65 white point, lightness goes from 0 to 100
+pub struct LAB {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+/// The polar form of `LAB`, hue is an angle in degrees from 0 to 360
+pub struct LCH {
+    pub l: f32,
+    pub c: f32,
+    pub h: f32,
+}
+
+impl LAB {
+    pub fn from_rgb_u8(r: &u8, g: &u8, b: &u8) -> LAB {
+        LAB::from_rgb_f32(
+            &((*r as f32) / 255_f32),
+            &((*g as f32) / 255_f32),
+            &((*b as f32) / 255_f32),
+        )
+    }
+
+    /// Same as `from_rgb_u8` but takes sRGB channels already normalized to 0.0-1.0
+    pub fn from_rgb_f32(f_r: &f32, f_g: &f32, f_b: &f32) -> LAB {
+        let r = color::srgb_to_linear(f_r);
+        let g = color::srgb_to_linear(f_g);
+        let b = color::srgb_to_linear(f_b);
+
+        let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / WHITE_X;
+        let y = (0.2126729 * r + 0.7151522 * g + 0.0721750 * b) / WHITE_Y;
+        let z = (0.0193339 * r + 0.119192 * g + 0.9503041 * b) / WHITE_Z;
+
+        let (f_x, f_y, f_z) = (lab_f(x), lab_f(y), lab_f(z));
+
+        LAB {
+            l: 116_f32 * f_y - 16_f32,
+            a: 500_f32 * (f_x - f_y),
+            b: 200_f32 * (f_y - f_z),
+        }
+    }
+
+    pub fn from_rgb(rgb: [u8; 3]) -> LAB {
+        LAB::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
+    }
+
+    pub fn from_rgb_vec(rgb: Vec<u8>) -> LAB {
+        LAB::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
+    }
+}
+
+impl LCH {
+    pub fn from_lab(lab: &LAB) -> LCH {
+        let (c, h) = color::chroma_hue(&lab.a, &lab.b);
+
+        LCH { l: lab.l, c, h }
+    }
+
+    pub fn from_rgb_u8(r: &u8, g: &u8, b: &u8) -> LCH {
+        LCH::from_lab(&LAB::from_rgb_u8(r, g, b))
+    }
+
+    pub fn from_rgb(rgb: [u8; 3]) -> LCH {
+        LCH::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
+    }
+
+    pub fn from_rgb_vec(rgb: Vec<u8>) -> LCH {
+        LCH::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
+    }
+}
+
+/// The cube root curve of CIELAB with its linear part near black
+fn lab_f(t: f32) -> f32 {
+    const DELTA: f32 = 6_f32 / 29_f32;
+
+    if t > DELTA * DELTA * DELTA {
+        t.cbrt()
+    } else {
+        t / (3_f32 * DELTA * DELTA) + 4_f32 / 29_f32
+    }
+}
+
+pub fn rgb_get_lightness(r: &u8, g: &u8, b: &u8) -> f32 {
+    LAB::from_rgb_u8(r, g, b).l
+}
+
+pub fn rgb_f32_get_lightness(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    LAB::from_rgb_f32(f_r, f_g, f_b).l
+}
+
+pub fn rgb_get_chroma(r: &u8, g: &u8, b: &u8) -> f32 {
+    LCH::from_rgb_u8(r, g, b).c
+}
+
+pub fn rgb_f32_get_chroma(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    LCH::from_lab(&LAB::from_rgb_f32(f_r, f_g, f_b)).c
+}
+
+pub fn rgb_get_hue(r: &u8, g: &u8, b: &u8) -> f32 {
+    LCH::from_rgb_u8(r, g, b).h
+}
+
+pub fn rgb_f32_get_hue(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    LCH::from_lab(&LAB::from_rgb_f32(f_r, f_g, f_b)).h
+}
@@ -0,0 +1,38 @@
+//! Luma as a weighted sum of the gamma encoded red, green and blue channels
+
+/// The red, green and blue weights of luma as defined by the video standards
+#[derive(Clone, Copy)]
+pub enum LumaStandard {
+    /// SD video, the classic 0.299 0.587 0.114 weights
+    Rec601,
+    /// HD video, matches the sRGB primaries
+    Rec709,
+    /// UHD video with its wider primaries
+    Rec2020,
+}
+
+impl LumaStandard {
+    pub fn coefficients(&self) -> [f32; 3] {
+        match self {
+            LumaStandard::Rec601 => [0.299, 0.587, 0.114],
+            LumaStandard::Rec709 => [0.2126, 0.7152, 0.0722],
+            LumaStandard::Rec2020 => [0.2627, 0.678, 0.0593],
+        }
+    }
+}
+
+pub fn rgb_get_luma(r: &u8, g: &u8, b: &u8, standard: &LumaStandard) -> f32 {
+    rgb_f32_get_luma(
+        &((*r as f32) / 255_f32),
+        &((*g as f32) / 255_f32),
+        &((*b as f32) / 255_f32),
+        standard,
+    )
+}
+
+/// Luma weights the gamma encoded channels, so no linearization happens here
+pub fn rgb_f32_get_luma(f_r: &f32, f_g: &f32, f_b: &f32, standard: &LumaStandard) -> f32 {
+    let [k_r, k_g, k_b] = standard.coefficients();
+
+    k_r * f_r + k_g * f_g + k_b * f_b
+}
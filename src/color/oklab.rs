@@ -0,0 +1,96 @@
+//! Oklab and its polar form OKLCH
+
+use crate::color;
+
+/// Oklab, lightness goes from 0 to 1
+pub struct OKLAB {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+/// The polar form of `OKLAB`, hue is an angle in degrees from 0 to 360
+pub struct OKLCH {
+    pub l: f32,
+    pub c: f32,
+    pub h: f32,
+}
+
+impl OKLAB {
+    pub fn from_rgb_u8(r: &u8, g: &u8, b: &u8) -> OKLAB {
+        OKLAB::from_rgb_f32(
+            &((*r as f32) / 255_f32),
+            &((*g as f32) / 255_f32),
+            &((*b as f32) / 255_f32),
+        )
+    }
+
+    /// Same as `from_rgb_u8` but takes sRGB channels already normalized to 0.0-1.0
+    pub fn from_rgb_f32(f_r: &f32, f_g: &f32, f_b: &f32) -> OKLAB {
+        let r = color::srgb_to_linear(f_r);
+        let g = color::srgb_to_linear(f_g);
+        let b = color::srgb_to_linear(f_b);
+
+        let l = (0.41222146 * r + 0.53633255 * g + 0.051445995 * b).cbrt();
+        let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+        let s = (0.08830246 * r + 0.28171885 * g + 0.6299787 * b).cbrt();
+
+        OKLAB {
+            l: 0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+            a: 1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+            b: 0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+        }
+    }
+
+    pub fn from_rgb(rgb: [u8; 3]) -> OKLAB {
+        OKLAB::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
+    }
+
+    pub fn from_rgb_vec(rgb: Vec<u8>) -> OKLAB {
+        OKLAB::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
+    }
+}
+
+impl OKLCH {
+    pub fn from_oklab(lab: &OKLAB) -> OKLCH {
+        let (c, h) = color::chroma_hue(&lab.a, &lab.b);
+
+        OKLCH { l: lab.l, c, h }
+    }
+
+    pub fn from_rgb_u8(r: &u8, g: &u8, b: &u8) -> OKLCH {
+        OKLCH::from_oklab(&OKLAB::from_rgb_u8(r, g, b))
+    }
+
+    pub fn from_rgb(rgb: [u8; 3]) -> OKLCH {
+        OKLCH::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
+    }
+
+    pub fn from_rgb_vec(rgb: Vec<u8>) -> OKLCH {
+        OKLCH::from_rgb_u8(&rgb[0], &rgb[1], &rgb[2])
+    }
+}
+
+pub fn rgb_get_lightness(r: &u8, g: &u8, b: &u8) -> f32 {
+    OKLAB::from_rgb_u8(r, g, b).l
+}
+
+pub fn rgb_f32_get_lightness(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    OKLAB::from_rgb_f32(f_r, f_g, f_b).l
+}
+
+pub fn rgb_get_chroma(r: &u8, g: &u8, b: &u8) -> f32 {
+    OKLCH::from_rgb_u8(r, g, b).c
+}
+
+pub fn rgb_f32_get_chroma(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    OKLCH::from_oklab(&OKLAB::from_rgb_f32(f_r, f_g, f_b)).c
+}
+
+pub fn rgb_get_hue(r: &u8, g: &u8, b: &u8) -> f32 {
+    OKLCH::from_rgb_u8(r, g, b).h
+}
+
+pub fn rgb_f32_get_hue(f_r: &f32, f_g: &f32, f_b: &f32) -> f32 {
+    OKLCH::from_oklab(&OKLAB::from_rgb_f32(f_r, f_g, f_b)).h
+}
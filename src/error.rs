@@ -0,0 +1,90 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The sort was stopped through its `CancelToken` before it finished
+    Cancelled,
+    /// The mask does not cover the image pixel for pixel
+    MaskSizeMismatch { image: (u32, u32), mask: (u32, u32) },
+    /// There is not exactly one list of spans per image row
+    IntervalRowCount { expected: u32, found: u32 },
+    /// A span of the row runs backwards, overlaps the previous one or ends past the image width
+    IntervalOutOfBounds { row: u32, end: u32, width: u32 },
+    /// A point of a line lies outside of the image
+    LinePointOutOfBounds { line: u32, point: (u32, u32) },
+    /// A point was already visited by an earlier line or earlier on the same line
+    LinePointRevisited { line: u32, point: (u32, u32) },
+    /// A span of the line runs backwards, overlaps the previous one or ends past the line's points
+    LineSpanOutOfBounds { line: u32, end: u32, len: u32 },
+    /// A threshold is not a finite number or the lower one is above the upper one
+    InvalidThreshold { lower: f32, upper: f32 },
+    /// The interval length is too short for the intervals to ever move on
+    InvalidIntervalLength(u32),
+    /// The edge threshold is negative or not a finite number
+    InvalidEdgeThreshold(f32),
+    /// The randomness is not a percentage in 0-100
+    InvalidRandomness(f32),
+    /// The threads for the sort could not be started
+    ThreadPool(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Cancelled => write!(f, "the sort was cancelled"),
+            Error::MaskSizeMismatch { image, mask } => write!(
+                f,
+                "the mask is {}x{} but the image is {}x{}",
+                mask.0, mask.1, image.0, image.1
+            ),
+            Error::IntervalRowCount { expected, found } => write!(
+                f,
+                "expected intervals for {} rows but got {}",
+                expected, found
+            ),
+            Error::IntervalOutOfBounds { row, end, width } => write!(
+                f,
+                "span ending at {} in row {} is out of order or past the width {}",
+                end, row, width
+            ),
+            Error::LinePointOutOfBounds { line, point } => write!(
+                f,
+                "point {},{} of line {} is outside of the image",
+                point.0, point.1, line
+            ),
+            Error::LinePointRevisited { line, point } => write!(
+                f,
+                "point {},{} of line {} was already visited by an earlier point",
+                point.0, point.1, line
+            ),
+            Error::LineSpanOutOfBounds { line, end, len } => write!(
+                f,
+                "span ending at {} of line {} is out of order or past its {} points",
+                end, line, len
+            ),
+            Error::InvalidThreshold { lower, upper } => write!(
+                f,
+                "thresholds {} and {} must be finite numbers with the lower one first",
+                lower, upper
+            ),
+            Error::InvalidIntervalLength(length) => {
+                write!(f, "interval length {} is too short", length)
+            }
+            Error::InvalidEdgeThreshold(threshold) => {
+                write!(
+                    f,
+                    "edge threshold {} must be a finite number of at least 0",
+                    threshold
+                )
+            }
+            Error::InvalidRandomness(randomness) => {
+                write!(f, "randomness {} must be within 0-100", randomness)
+            }
+            Error::ThreadPool(reason) => write!(f, "could not start the threads: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,73 +1,272 @@
+use image::GenericImageView;
+use image::Rgba;
+use rand::Rng;
 
-use image::RgbImage;
-
+use crate::color;
+use crate::color::luma;
 use crate::math;
+use crate::rng;
+use crate::sorting::SortKey;
+use crate::Error;
+use crate::Result;
 
 pub enum IntervalType {
-    HorizontalRow(Vec<Vec<u32>>),
-    DynamicLine(Vec<Vec<(u32, u32)>>),
+    /// The spans of every row, one list per row from top to bottom
+    HorizontalRow(Vec<Vec<Span>>),
+    DynamicLine(Vec<Line>),
 }
 
-pub enum Interval {
-    Threshold,
-    Random,
-    EntireRow,
-    AbsSinWave,
-    Angle
+/// A run of pixels from `start` up to but not including `end`
+///
+/// Spans with `sort` unset keep their pixels where they are
+#[derive(Clone, Copy)]
+pub struct Span {
+    pub start: u32,
+    pub end: u32,
+    pub sort: bool,
+}
+
+impl Span {
+    pub fn new(start: u32, end: u32, sort: bool) -> Span {
+        Span { start, end, sort }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A path of pixels through the image, its spans index into `points`
+pub struct Line {
+    pub points: Vec<(u32, u32)>,
+    pub spans: Vec<Span>,
+}
+
+impl Line {
+    /// The line through the waypoints, the stretch between two neighbouring waypoints is one span
+    pub fn from_waypoints(waypoints: &[(u32, u32)]) -> Line {
+        let mut points = Vec::new();
+        let mut spans = Vec::new();
+
+        for pair in waypoints.windows(2) {
+            let start = points.len() as u32;
+
+            points.extend(math::points_between(&pair[0], &pair[1]));
+            spans.push(Span::new(start, points.len() as u32, true));
+        }
+
+        Line { points, spans }
+    }
+}
+
+/// Turns the interval ends of a row into sorted spans, the first one starts at 0
+pub fn spans_from_ends(ends: &[u32]) -> Vec<Span> {
+    let mut start = 0;
+
+    ends.iter()
+        .map(|end| {
+            let span = Span::new(start, *end, true);
+            start = *end;
+            span
+        })
+        .collect()
+}
+
+/// Splits an image into the intervals that get sorted
+///
+/// Implement this to add an interval mode of your own and pass it to `PixelSorter::interval`
+pub trait IntervalGenerator: Sync {
+    /// Finds the intervals of the image, random choices should only depend on the seed
+    fn generate(&self, image: &dyn PixelSource, seed: u64) -> Result<IntervalType>;
+}
+
+/// Read access to an image of any pixel type, with channels normalized to 0.0-1.0
+pub trait PixelSource {
+    fn dimensions(&self) -> (u32, u32);
+
+    fn rgba(&self, x: u32, y: u32) -> [f32; 4];
+}
+
+impl<I: GenericImageView> PixelSource for I {
+    fn dimensions(&self) -> (u32, u32) {
+        GenericImageView::dimensions(self)
+    }
+
+    fn rgba(&self, x: u32, y: u32) -> [f32; 4] {
+        color::to_rgba_f32(&self.get_pixel(x, y))
+    }
+}
+
+/// Lets the generic interval functions read a `PixelSource`
+struct SourceView<'a>(&'a dyn PixelSource);
+
+impl GenericImageView for SourceView<'_> {
+    type Pixel = Rgba<f32>;
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    fn bounds(&self) -> (u32, u32, u32, u32) {
+        let (width, height) = self.0.dimensions();
+        (0, 0, width, height)
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Rgba<f32> {
+        Rgba(self.0.rgba(x, y))
+    }
+}
+
+/// How threshold intervals use the pixels whose measure is in range
+pub enum ThresholdMode {
+    /// Every pixel in range starts a new interval, see `threshold`
+    Cut,
+    /// Runs of pixels in range are sorted, the rest stay in place, see `threshold_spans`
+    Span,
 }
-pub fn get_interval(
-    interval_method: &Interval,
-    image: &RgbImage,
-    char_length: &u32,
-    lower_threshold: &f32,
-    upper_threshold: &f32,
-    angle: &u32,
-    threshold_inclusive: &bool,
-) -> IntervalType {
-    match interval_method {
-        Interval::Threshold => IntervalType::HorizontalRow(threshold(
-            image,
-            lower_threshold,
-            upper_threshold,
-            threshold_inclusive,
-        )),
-        Interval::Random => IntervalType::HorizontalRow(random(image, char_length)),
-        Interval::EntireRow => IntervalType::HorizontalRow(entire_row(image)),
-        Interval::AbsSinWave => IntervalType::DynamicLine(sin_wave(image, char_length)),
-        Interval::Angle => IntervalType::DynamicLine(intervals_from_angle(image, angle)),
-    }
-}
-pub fn entire_row(image: &RgbImage) -> Vec<Vec<u32>> {
-    let mut intervals: Vec<Vec<u32>> = Vec::new();
+
+/// Intervals from the pixels whose measure is within the range
+pub struct Threshold<K: SortKey> {
+    pub lower: f32,
+    pub upper: f32,
+    /// Use the pixels outside of the range instead of inside
+    pub inclusive: bool,
+    /// What the range applies to, any `SortMethod` or other `SortKey`
+    pub measure: K,
+    pub mode: ThresholdMode,
+}
+
+impl<K: SortKey> IntervalGenerator for Threshold<K> {
+    fn generate(&self, image: &dyn PixelSource, _seed: u64) -> Result<IntervalType> {
+        let find_intervals = match self.mode {
+            ThresholdMode::Cut => threshold,
+            ThresholdMode::Span => threshold_spans,
+        };
+
+        Ok(IntervalType::HorizontalRow(find_intervals(
+            &SourceView(image),
+            &self.lower,
+            &self.upper,
+            &self.inclusive,
+            &self.measure,
+        )?))
+    }
+}
+
+/// Intervals of random length, see `random`
+pub struct Random {
+    /// The length multiplier of the intervals, at least 2
+    pub length: u32,
+}
+
+impl IntervalGenerator for Random {
+    fn generate(&self, image: &dyn PixelSource, seed: u64) -> Result<IntervalType> {
+        Ok(IntervalType::HorizontalRow(random(
+            &SourceView(image),
+            &self.length,
+            &seed,
+        )?))
+    }
+}
+
+/// Every row is a single interval
+pub struct EntireRow;
+
+impl IntervalGenerator for EntireRow {
+    fn generate(&self, image: &dyn PixelSource, _seed: u64) -> Result<IntervalType> {
+        Ok(IntervalType::HorizontalRow(entire_row(&SourceView(image))))
+    }
+}
+
+/// Sorts the runs of pixels between edges, see `edges`
+pub struct Edges {
+    /// How strong the gradient must be for a pixel to count as an edge, a hard step is 1.0
+    pub threshold: f32,
+}
+
+impl IntervalGenerator for Edges {
+    fn generate(&self, image: &dyn PixelSource, _seed: u64) -> Result<IntervalType> {
+        Ok(IntervalType::HorizontalRow(edges(
+            &SourceView(image),
+            &self.threshold,
+        )?))
+    }
+}
+
+/// Zig zag lines following the absolute value of a sine wave, see `sin_wave`
+pub struct SinWave {
+    /// The vertical step between the points of the wave, at least 1
+    pub length: u32,
+}
+
+impl IntervalGenerator for SinWave {
+    fn generate(&self, image: &dyn PixelSource, _seed: u64) -> Result<IntervalType> {
+        Ok(IntervalType::DynamicLine(sin_wave(
+            &SourceView(image),
+            &self.length,
+        )?))
+    }
+}
+
+/// Parallel straight lines at an angle in degrees, see `intervals_from_angle`
+pub struct Angle {
+    pub angle: u32,
+}
+
+impl IntervalGenerator for Angle {
+    fn generate(&self, image: &dyn PixelSource, _seed: u64) -> Result<IntervalType> {
+        Ok(IntervalType::DynamicLine(intervals_from_angle(
+            &SourceView(image),
+            &self.angle,
+        )))
+    }
+}
+
+pub fn entire_row<I: GenericImageView>(image: &I) -> Vec<Vec<Span>> {
+    let mut intervals: Vec<Vec<Span>> = Vec::new();
 
     let (width, height) = image.dimensions();
 
     for _y in 0..height {
-        let row: Vec<u32> = Vec::from([0, width]);
+        let row: Vec<Span> = vec![Span::new(0, width, true)];
         intervals.push(row);
     }
 
-    return intervals;
+    intervals
 }
 
-pub fn random(image: &RgbImage, char_length: &u32) -> Vec<Vec<u32>> {
-    let mut intervals: Vec<Vec<u32>> = Vec::new();
+/// Splits every row into intervals of random length,
+/// the same seed always gives the same intervals
+///
+/// Lengths below 2 would never move past the start of the row and are rejected
+pub fn random<I: GenericImageView>(
+    image: &I,
+    char_length: &u32,
+    seed: &u64,
+) -> Result<Vec<Vec<Span>>> {
+    if *char_length < 2 {
+        return Err(Error::InvalidIntervalLength(*char_length));
+    }
+
+    let mut intervals: Vec<Vec<Span>> = Vec::new();
 
     let (width, height) = image.dimensions();
 
-    let mut char_length = *char_length;
+    let char_length = *char_length;
 
-    if char_length < 1 {
-        char_length = 2;
-    }
-
-    for _y in 0..height {
+    for y in 0..height {
         let mut row: Vec<u32> = Vec::new();
 
+        let mut rng = rng::row_rng(*seed, rng::RngPurpose::IntervalLength, y as u64);
+
         let mut x = 0;
 
         loop {
-            x += (char_length as f32 * rand::random::<f32>()) as u32;
+            x += (char_length as f32 * rng.gen::<f32>()) as u32;
 
             if x > width {
                 break;
@@ -78,19 +277,26 @@ pub fn random(image: &RgbImage, char_length: &u32) -> Vec<Vec<u32>> {
         if x < width {
             row.push(width);
         }
-        intervals.push(row);
+        intervals.push(spans_from_ends(&row));
     }
 
-    return intervals;
+    Ok(intervals)
 }
 
-pub fn threshold(
-    image: &RgbImage,
+/// Cuts the rows on pixels whose measure falls within the thresholds,
+/// or outside of them when inclusive.
+///
+/// The thresholds use the scale of the measure, e.g. 0.0-1.0 for HSL lightness or hue
+pub fn threshold<I: GenericImageView, K: SortKey + ?Sized>(
+    image: &I,
     lower_threshold: &f32,
     upper_threshold: &f32,
     inclusive: &bool,
-) -> Vec<Vec<u32>> {
-    let mut intervals: Vec<Vec<u32>> = Vec::new();
+    measure: &K,
+) -> Result<Vec<Vec<Span>>> {
+    validate_thresholds(lower_threshold, upper_threshold)?;
+
+    let mut intervals: Vec<Vec<Span>> = Vec::new();
 
     let (width, height) = image.dimensions();
 
@@ -98,166 +304,367 @@ pub fn threshold(
         let mut row: Vec<u32> = Vec::new();
 
         for x in 0..width {
-            let pixel: &image::Rgb<u8> = image.get_pixel(x, y);
-            let level = crate::color::hsl::rgb_get_lightness(&pixel[0], &pixel[1], &pixel[2]);
+            let level = measure.key(&color::to_rgba_f32(&image.get_pixel(x, y)));
 
-            if !*inclusive {
-                if level > *lower_threshold && level < *upper_threshold {
-                    row.push(x);
-                }
-            } else {
-                if level < *lower_threshold || level > *upper_threshold {
-                    row.push(x);
-                }
+            if in_threshold(level, *lower_threshold, *upper_threshold, *inclusive) {
+                row.push(x);
             }
         }
         if !row.is_empty() && row.last().unwrap() < &width {
             row.push(width);
         }
-        intervals.push(row);
+        intervals.push(spans_from_ends(&row));
     }
 
-    return intervals;
+    Ok(intervals)
 }
 
-pub fn extend_dynamic_line_interval_to_width_2(
-    width: &u32,
-    height: &u32,
-    line: &Vec<(u32, u32)>,
-) -> Vec<Vec<(u32, u32)>> {
+/// Groups every run of neighbouring pixels whose measure falls within the thresholds,
+/// or outside of them when inclusive, into one sorted span.
+///
+/// The runs of the other pixels become unsorted spans, so they stay where they are
+pub fn threshold_spans<I: GenericImageView, K: SortKey + ?Sized>(
+    image: &I,
+    lower_threshold: &f32,
+    upper_threshold: &f32,
+    inclusive: &bool,
+    measure: &K,
+) -> Result<Vec<Vec<Span>>> {
+    validate_thresholds(lower_threshold, upper_threshold)?;
 
-    let mut intervals: Vec<Vec<(u32, u32)>> = Vec::new();
+    let mut intervals: Vec<Vec<Span>> = Vec::new();
 
-    if line.len() < 1 {
-        return intervals;
+    let (width, height) = image.dimensions();
+
+    for y in 0..height {
+        let mut row: Vec<Span> = Vec::new();
+
+        for x in 0..width {
+            let level = measure.key(&color::to_rgba_f32(&image.get_pixel(x, y)));
+            let sort = in_threshold(level, *lower_threshold, *upper_threshold, *inclusive);
+
+            match row.last_mut() {
+                Some(span) if span.sort == sort => span.end = x + 1,
+                _ => row.push(Span::new(x, x + 1, sort)),
+            }
+        }
+        intervals.push(row);
     }
 
-    for _ in 0..*width {
-        intervals.push(Vec::new());
+    Ok(intervals)
+}
+
+/// Is the level strictly within the thresholds, or outside of them when inclusive
+fn in_threshold(level: f32, lower: f32, upper: f32, inclusive: bool) -> bool {
+    if !inclusive {
+        level > lower && level < upper
+    } else {
+        level < lower || level > upper
     }
-    
-    let mut line_iter = line.iter();
-    let mut start = line_iter.next().unwrap();
+}
 
-    for next  in line_iter {
+fn validate_thresholds(lower_threshold: &f32, upper_threshold: &f32) -> Result<()> {
+    if !lower_threshold.is_finite()
+        || !upper_threshold.is_finite()
+        || lower_threshold > upper_threshold
+    {
+        return Err(Error::InvalidThreshold {
+            lower: *lower_threshold,
+            upper: *upper_threshold,
+        });
+    }
+    Ok(())
+}
 
-        let dist_x = (next.0 as i32 - start.0 as i32).abs() as u32;
-        let dist_y = (next.1 as i32 - start.1 as i32).abs() as u32;
+/// Finds the edges with a Sobel filter over the Rec.709 luma and sorts the runs between them,
+/// the edge pixels themselves are left in place
+pub fn edges<I: GenericImageView>(image: &I, edge_threshold: &f32) -> Result<Vec<Vec<Span>>> {
+    if !edge_threshold.is_finite() || *edge_threshold < 0_f32 {
+        return Err(Error::InvalidEdgeThreshold(*edge_threshold));
+    }
 
-        let angle = (dist_y as f64 / dist_x as f64).atan();
+    let (width, height) = image.dimensions();
 
-        for (i, interval ) in (0..(*width )).step_by(1).zip(intervals.iter_mut()) {
+    let mut levels = Vec::with_capacity(width as usize * height as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let p = color::to_rgba_f32(&image.get_pixel(x, y));
+            levels.push(luma::rgb_f32_get_luma(
+                &p[0],
+                &p[1],
+                &p[2],
+                &luma::LumaStandard::Rec709,
+            ));
+        }
+    }
 
-            let y = (i as f64 * angle.tan()) as u32 ;
+    let magnitude = math::sobel_magnitude(&levels, width, height);
 
-            let x = if dist_x > i {
-                0
+    let mut intervals: Vec<Vec<Span>> = Vec::new();
+
+    for y in 0..height {
+        let mut row: Vec<Span> = Vec::new();
+
+        for x in 0..width {
+            let sort = magnitude[(y * width + x) as usize] <= *edge_threshold;
+
+            match row.last_mut() {
+                Some(span) if span.sort == sort => span.end = x + 1,
+                _ => row.push(Span::new(x, x + 1, sort)),
             }
-             else {
-                i - dist_x
-             };
-
-            interval.push((x, match next.1.checked_sub(y) {
-                Some(y) => y,
-                None => 0,
-             }));
-            interval.push((i, next.1));
         }
-
-        start = next;
+        intervals.push(row);
     }
 
-
-intervals
+    Ok(intervals)
 }
-pub fn extend_dynamic_line_interval_to_width(
+
+/// Copies of the wave moved right and left by every offset up to the width,
+/// points that would fall outside of the image are left out
+fn extend_dynamic_line_interval_to_width(
     width: &u32,
     height: &u32,
-    line: &Vec<(u32, u32)>,
+    line: &[(u32, u32)],
 ) -> Vec<Vec<(u32, u32)>> {
     let mut intervals: Vec<Vec<(u32, u32)>> = Vec::new();
 
     for i in 0..*width {
         let mut t2 = Vec::new();
 
-        line.iter().for_each(|p| {
+        line.iter().filter(|p| p.1 < *height).for_each(|p| {
             if p.0 + i < *width {
                 t2.push((p.0 + i, p.1));
-            } 
-            // else {
-
-            //     let h = if (p.1 as i32 - (p.0 as i32 + i as i32 - *width as i32) < 0) {
-            //         0
-            //     } else {
-            //         p.1 - (p.0 + i - width)
-            //     };
-
-            //     t2.push((width - 1, h));
-            // }
-            
-            
+            }
+
             if p.0 as i32 - i as i32 > 0 {
                 t2.push((p.0 - i, p.1));
-            } 
+            }
         });
 
         intervals.push(t2);
     }
 
-    
-
     intervals
 }
-pub fn sin_wave(image: &RgbImage, char_length: &u32) -> Vec<Vec<(u32, u32)>> {
+
+/// Lines of the image width following the wave, a length of 0 would never get down the image
+pub fn sin_wave<I: GenericImageView>(image: &I, char_length: &u32) -> Result<Vec<Line>> {
+    if *char_length == 0 {
+        return Err(Error::InvalidIntervalLength(*char_length));
+    }
+
     let mut intervals = Vec::new();
 
     let (width, height) = image.dimensions();
 
+    if width == 0 || height == 0 {
+        return Ok(Vec::new());
+    }
+
     let mut y = 0;
     let mut x: f64 = 0_f64;
 
+    // the wave is cut off at the right edge so narrow images keep every point inside
     while y < height {
-        intervals.push((((x).sin().abs() * 100_f64) as u32, y));
+        intervals.push((((x.sin().abs() * 100_f64) as u32).min(width - 1), y));
 
         x += std::f64::consts::PI / 4_f64;
         y += char_length;
     }
 
-    extend_dynamic_line_interval_to_width(&width,&height, &intervals)
+    let lines: Vec<Line> = extend_dynamic_line_interval_to_width(&width, &height, &intervals)
+        .iter()
+        .map(|line| Line::from_waypoints(line))
+        .collect();
+
+    Ok(drop_revisited_points(lines, width, height))
 }
 
-pub fn intervals_from_angle(image: &RgbImage, angle: &u32) -> Vec<Vec<(u32, u32)>> {
+/// Keeps every pixel only on the first line and at the first point that visits it
+/// and drops points outside of the image, the spans shrink along with the points they lose
+fn drop_revisited_points(lines: Vec<Line>, width: u32, height: u32) -> Vec<Line> {
+    let mut visited = vec![false; width as usize * height as usize];
+
+    lines
+        .into_iter()
+        .map(|line| {
+            // kept[i] is the number of points kept before the old point i
+            let mut kept = Vec::with_capacity(line.points.len() + 1);
+            let mut points = Vec::new();
+
+            for point in line.points {
+                kept.push(points.len() as u32);
 
+                if point.0 >= width || point.1 >= height {
+                    continue;
+                }
+
+                let index = point.1 as usize * width as usize + point.0 as usize;
+
+                if !visited[index] {
+                    visited[index] = true;
+                    points.push(point);
+                }
+            }
+            kept.push(points.len() as u32);
+
+            let spans = line
+                .spans
+                .iter()
+                .map(|span| {
+                    Span::new(
+                        kept[span.start as usize],
+                        kept[span.end as usize],
+                        span.sort,
+                    )
+                })
+                .collect();
+
+            Line { points, spans }
+        })
+        .collect()
+}
+
+/// Parallel lines at the given angle in degrees that together cover every pixel exactly once.
+///
+/// 0 runs left to right and 90 top to bottom, from 180 on the lines run the other way
+pub fn intervals_from_angle<I: GenericImageView>(image: &I, angle: &u32) -> Vec<Line> {
     let (width, height) = image.dimensions();
 
-    let angle = *angle % 180;
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    let (sin, cos) = math::deg_to_rad((*angle % 360) as f64).sin_cos();
+
+    // pixels at the same rounded distance from the line through the origin form one line
+    let offset = |x: u32, y: u32| (y as f64 * cos - x as f64 * sin).round() as i64;
 
-    if angle <= 90 {
+    let corners = [
+        (0, 0),
+        (width - 1, 0),
+        (0, height - 1),
+        (width - 1, height - 1),
+    ];
+    let offsets = corners.map(|(x, y)| offset(x, y));
+    let min_offset = *offsets.iter().min().unwrap();
+    let max_offset = *offsets.iter().max().unwrap();
 
-        let radians = math::deg_to_rad(angle  as f64); 
+    let mut lines: Vec<Vec<(f64, (u32, u32))>> =
+        (min_offset..=max_offset).map(|_| Vec::new()).collect();
 
-        let hyp =  height as f64 / radians.sin();
+    for y in 0..height {
+        for x in 0..width {
+            let along = x as f64 * cos + y as f64 * sin;
 
-        let mut x = radians.cos() * hyp;
-        let mut y = height as f64 - 1_f64;
+            lines[(offset(x, y) - min_offset) as usize].push((along, (x, y)));
+        }
+    }
 
-        // if x as u32 > width {
-        //     let hyp = width as f64 / radians.cos();
+    lines
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .map(|mut line| {
+            line.sort_by(|a, b| a.0.total_cmp(&b.0));
 
-        //     x = width as f64 - 1_f64;
-        //     y = hyp * radians.sin();
-        // }
+            let points: Vec<(u32, u32)> = line.into_iter().map(|(_, point)| point).collect();
+            let spans = vec![Span::new(0, points.len() as u32, true)];
 
-        let start = (0_u32, 0_u32);
-        let end = (x as u32, y as u32);
+            Line { points, spans }
+        })
+        .collect()
+}
 
-        println!("{} {}    {} {}", 0, 0, end.0, end.1);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sorting::SortMethod;
+    use image::RgbImage;
+
+    fn test_image(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([
+                (x * 37 % 256) as u8,
+                (y * 91 % 256) as u8,
+                ((x + y) * 13) as u8,
+            ])
+        })
+    }
 
-        let v = vec![start, end];
+    fn sorted_pixels(image: &RgbImage) -> Vec<[u8; 3]> {
+        let mut pixels: Vec<[u8; 3]> = image.pixels().map(|p| p.0).collect();
+        pixels.sort();
+        pixels
+    }
 
-        return extend_dynamic_line_interval_to_width_2(&width, &height, &v);
+    #[test]
+    fn sin_wave_sorts_narrow_images() {
+        for (width, height) in [(1, 1), (7, 3), (50, 500), (99, 50), (100, 50)] {
+            let image = test_image(width, height);
+
+            for length in [1, 2, 50] {
+                let sorted = crate::PixelSorter::new()
+                    .interval(SinWave { length })
+                    .apply(&image)
+                    .unwrap();
+
+                assert_eq!(sorted_pixels(&sorted), sorted_pixels(&image));
+
+                // every span of every line ends up ordered by brightness
+                let lines = sin_wave(&image, &length).unwrap();
+                let brightness = |image: &RgbImage, p: &(u32, u32)| {
+                    SortMethod::Brightness.key(&color::to_rgba_f32(image.get_pixel(p.0, p.1)))
+                };
+
+                for line in &lines {
+                    for span in &line.spans {
+                        let points = &line.points[span.start as usize..span.end as usize];
+                        assert!(points
+                            .windows(2)
+                            .all(|w| brightness(&sorted, &w[0]) <= brightness(&sorted, &w[1])));
+                    }
+                }
+            }
+        }
     }
 
-    vec![]
+    #[test]
+    fn lengths_that_cannot_advance_are_rejected() {
+        let image = test_image(7, 3);
 
+        for length in [0, 1] {
+            let result = Random { length }.generate(&image, 0);
+            assert!(matches!(result, Err(Error::InvalidIntervalLength(_))));
+        }
+
+        let result = SinWave { length: 0 }.generate(&image, 0);
+        assert!(matches!(result, Err(Error::InvalidIntervalLength(0))));
+    }
+
+    #[test]
+    fn angle_lines_cover_every_pixel_once() {
+        for (width, height) in [(1, 1), (7, 3), (3, 7), (1, 9), (9, 1)] {
+            let image = RgbImage::new(width, height);
+
+            for angle in 0..360 {
+                let mut visits = vec![0; (width * height) as usize];
+
+                for line in intervals_from_angle(&image, &angle) {
+                    for (x, y) in line.points {
+                        assert!(x < width && y < height, "{}x{} at {}", width, height, angle);
+                        visits[(y * width + x) as usize] += 1;
+                    }
+                }
+
+                assert!(
+                    visits.iter().all(|v| *v == 1),
+                    "{}x{} at {}",
+                    width,
+                    height,
+                    angle
+                );
+            }
+        }
+    }
 }
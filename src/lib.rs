@@ -1,168 +1,413 @@
-use image::GenericImageView;
+use std::sync::atomic;
+use std::sync::atomic::AtomicU32;
+
 use image::ImageBuffer;
-use image::Rgb;
-use image::RgbImage;
+use image::Pixel;
+use image::Primitive;
+use num_traits::NumCast;
+use num_traits::ToPrimitive;
+use rand::Rng;
 
 pub mod color;
+mod error;
 pub mod interval;
+pub mod mask;
 pub mod math;
+mod parallel;
+pub mod progress;
+mod rng;
+mod sorter;
 pub mod sorting;
 
-const RGB_RED: image::Rgb<u8> = image::Rgb([255_u8, 0_u8, 0_u8]);
+pub use error::Error;
+pub use error::Result;
+pub use mask::Mask;
+pub use sorter::Direction;
+pub use sorter::MaskMode;
+pub use sorter::PixelSorter;
 
-pub fn get_sorted_image(
-    image: &RgbImage,
-    mask_data: Option<&Vec<Vec<bool>>>,
+/// Sorts the intervals of the image into a new image with the settings of the sorter
+///
+/// The intervals are used as given, so the sorter's interval settings and direction are ignored.
+/// Use `PixelSorter::apply` to find the intervals and sort in one go
+pub fn get_sorted_image<P>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
     intervals: &interval::IntervalType,
-    randomness: f32,
-    sort_method: &sorting::SortMethod,
-) -> RgbImage {
-    let result = match intervals {
+    sorter: &PixelSorter,
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>>
+where
+    P: Pixel + Send + Sync,
+    P::Subpixel: Send + Sync,
+{
+    let mask_data = sorter.mask.as_ref();
+
+    match intervals {
         interval::IntervalType::DynamicLine(i) => {
-            get_sorted_image_raw2(image, &i, randomness, sort_method)
+            get_sorted_image_raw2(image, mask_data, i, sorter)
         }
         interval::IntervalType::HorizontalRow(i) => {
-            get_sorted_image_raw(image, mask_data, &i, randomness, sort_method)
+            get_sorted_image_raw(image, mask_data, i, sorter)
         }
-    };
-
-    result
+    }
 }
 
-pub fn sort_image(
-    image: &mut RgbImage,
-    mask_data: Option<&Vec<Vec<bool>>>,
-    intervals: &Vec<Vec<u32>>,
-    randomness: f32,
-    sort_method: &sorting::SortMethod,
-) {
-    let result = get_sorted_image_raw(image, mask_data, intervals, randomness, sort_method);
+pub fn sort_image<P>(
+    image: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+    intervals: &[Vec<interval::Span>],
+    sorter: &PixelSorter,
+) -> Result<()>
+where
+    P: Pixel + Send + Sync,
+    P::Subpixel: Send + Sync,
+{
+    let result = get_sorted_image_raw(image, sorter.mask.as_ref(), intervals, sorter)?;
 
     result.iter().zip(image.iter_mut()).for_each(|(x, y)| {
         *y = *x;
     });
+
+    Ok(())
 }
 
-pub fn get_sorted_image_raw(
-    image: &RgbImage,
-    mask_data: Option<&Vec<Vec<bool>>>,
-    intervals: &Vec<Vec<u32>>,
-    randomness: f32,
-    sort_method: &sorting::SortMethod,
-) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let mut interval_iter = intervals.iter();
+/// Sorts the spans of every row, the mask is passed separately so it can be turned along with the image
+///
+/// Pixels outside of the sorted spans keep their value from the source image
+pub fn get_sorted_image_raw<P>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    mask_data: Option<&Mask>,
+    intervals: &[Vec<interval::Span>],
+    sorter: &PixelSorter,
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>>
+where
+    P: Pixel + Send + Sync,
+    P::Subpixel: Send + Sync,
+{
+    let PixelSorter {
+        randomness,
+        sort_method,
+        alpha_mode,
+        order,
+        mask_mode,
+        seed,
+        progress,
+        cancel,
+        ..
+    } = sorter;
+
+    let (randomness, seed) = (*randomness, *seed);
+    let (progress, cancel) = (progress.as_ref(), cancel.as_ref());
+
     let (width, height) = image.dimensions();
 
-    let mut output = RgbImage::new(width, height);
+    validate_randomness(randomness)?;
+    validate_row_intervals(intervals, width, height)?;
 
-    let mut last_progress = 0;
+    if let Some(mask) = mask_data {
+        validate_mask(mask, width, height)?;
+    }
 
-    for y in 0..height {
-        // let mut row: Vec<Rgb<u8>> = vec![image::Rgb([0,0,0]); width as usize];
+    let mut output = ImageBuffer::new(width, height);
 
-        let mut x_min = 0;
+    let rows_done = AtomicU32::new(0);
 
-        if let Some(i) = interval_iter.next() {
-            for x_max in i {
-                let mut interval: Vec<Rgb<u8>> = Vec::new();
+    parallel::for_each_row(&mut output, |y, row| {
+        if is_cancelled(cancel) {
+            return;
+        }
 
-                for x in x_min..*x_max {
-                    interval.push(image.get_pixel(x, y).clone());
-                }
+        let weight = |x: u32| mask_data.map_or(1_f32, |mask| mask.weight(x, y));
+        let spans = spans_to_sort(&intervals[y as usize], *mask_mode, |x| weight(x) > 0_f32);
 
-                if randomness > 0f32 && rand::random::<f32>() * 100f32 < randomness {
-                } else {
-                    interval.sort_by(sorting::get_sort_func(sort_method));
-                }
+        let mut rng = rng::row_rng(seed, rng::RngPurpose::SkipSort, y as u64);
 
-                for (x, pix) in (x_min..*x_max).zip(interval) {
-                    if let Some(mask) = mask_data {
-                        if mask[x as usize][y as usize] {
-                            output.put_pixel(x, y, pix);
-                        } else {
-                            output.put_pixel(x, y, *image.get_pixel(x, y));
-                        }
-                    } else {
-                        output.put_pixel(x, y, pix);
-                    }
-                }
+        let row_start = y as usize * row.len();
+        row.copy_from_slice(&image.as_raw()[row_start..row_start + row.len()]);
+
+        for (n, span) in spans.iter().enumerate() {
+            let mut interval: Vec<P> = Vec::new();
+
+            for x in span.start..span.end {
+                interval.push(*image.get_pixel(x, y));
+            }
 
-                x_min = *x_max;
+            if randomness <= 0f32 || rng.gen::<f32>() * 100f32 >= randomness {
+                sorting::sort_interval(
+                    &mut interval,
+                    sort_method.as_ref(),
+                    alpha_mode,
+                    order.is_descending(y, n as u32),
+                );
             }
 
-            let progress = ((y + 1) as f32 / height as f32 * 100f32) as u32;
+            // masked out pixels already hold their source value
+            for (x, pix) in (span.start..span.end).zip(interval) {
+                let weight = weight(x);
+
+                if weight > 0_f32 {
+                    put_row_pixel(row, x, &blend(image.get_pixel(x, y), &pix, weight));
+                }
+            }
+        }
+
+        report_progress(progress, &rows_done, height);
+    });
+
+    if is_cancelled(cancel) {
+        return Err(Error::Cancelled);
+    }
+
+    Ok(output)
+}
 
-            if progress % 10 == 0 && progress != last_progress {
-                println!("Sort progress: {}%", progress);
+/// The spans that get sorted, with `MaskMode::Split` they are cut into the runs that are in the mask
+fn spans_to_sort(
+    spans: &[interval::Span],
+    mask_mode: MaskMode,
+    in_mask: impl Fn(u32) -> bool,
+) -> Vec<interval::Span> {
+    let mut sorted_spans = Vec::new();
+
+    for span in spans.iter().filter(|span| span.sort) {
+        if let MaskMode::Restore = mask_mode {
+            sorted_spans.push(*span);
+            continue;
+        }
+
+        let mut start = span.start;
+
+        for i in span.start..=span.end {
+            if i == span.end || !in_mask(i) {
+                if i > start {
+                    sorted_spans.push(interval::Span::new(start, i, true));
+                }
+                start = i + 1;
             }
-            last_progress = progress;
-            // sorted_pixels.push(row);
-        } else {
-            println!("Early break for some reason!!!");
-            break;
         }
     }
 
-   
-    output
+    sorted_spans
 }
 
-pub fn create_bool_2d_vector(width: usize, height: usize) -> Vec<Vec<bool>> {
-    let mut bool_2d_vector = Vec::with_capacity(height);
-    for _ in 0..height {
-        let row = vec![true; width];
-        bool_2d_vector.push(row);
+/// Mixes the sorted pixel over the source pixel, a weight of 1.0 or more gives the sorted pixel as is
+fn blend<P: Pixel>(source: &P, sorted: &P, weight: f32) -> P {
+    if weight >= 1_f32 {
+        return *sorted;
     }
-    bool_2d_vector
+
+    // integer channels are rounded, float channels keep the exact mix
+    let round = P::Subpixel::DEFAULT_MAX_VALUE.to_f32().unwrap_or(1_f32) > 1_f32;
+
+    sorted.map2(source, |s, o| {
+        let (s_f, o_f) = (s.to_f32().unwrap_or(0_f32), o.to_f32().unwrap_or(0_f32));
+        let mixed = o_f + (s_f - o_f) * weight;
+
+        NumCast::from(if round { mixed.round() } else { mixed }).unwrap_or(o)
+    })
 }
 
-pub fn get_sorted_image_raw2(
-    image: &RgbImage,
-    intervals: &Vec<Vec<(u32, u32)>>,
-    randomness: f32,
-    sort_method: &sorting::SortMethod,
-) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let (width, height) = image.dimensions();
+/// Writes a pixel into the channel data of a single image row
+fn put_row_pixel<P: Pixel>(row: &mut [P::Subpixel], x: u32, pixel: &P) {
+    let channels = P::CHANNEL_COUNT as usize;
+    let start = x as usize * channels;
+
+    row[start..start + channels].copy_from_slice(pixel.channels());
+}
+
+/// Counts one more finished row or line and passes the new count on
+fn report_progress(progress: &dyn progress::Progress, done: &AtomicU32, total: u32) {
+    let done = done.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+
+    progress.report(done, total);
+}
+
+fn is_cancelled(cancel: Option<&progress::CancelToken>) -> bool {
+    cancel.is_some_and(|c| c.is_cancelled())
+}
+
+fn validate_randomness(randomness: f32) -> Result<()> {
+    if !(0_f32..=100_f32).contains(&randomness) {
+        return Err(Error::InvalidRandomness(randomness));
+    }
+    Ok(())
+}
 
-    let mut last_progress = 0;
+/// Checks the mask covers the image pixel for pixel
+pub(crate) fn validate_mask(mask: &Mask, width: u32, height: u32) -> Result<()> {
+    if mask.dimensions() != (width, height) {
+        return Err(Error::MaskSizeMismatch {
+            image: (width, height),
+            mask: mask.dimensions(),
+        });
+    }
+    Ok(())
+}
 
-    let mut output = RgbImage::new(width, height);
+/// Checks there is one row of spans per image row and that they are in order within the width
+fn validate_row_intervals(
+    intervals: &[Vec<interval::Span>],
+    width: u32,
+    height: u32,
+) -> Result<()> {
+    if intervals.len() != height as usize {
+        return Err(Error::IntervalRowCount {
+            expected: height,
+            found: intervals.len() as u32,
+        });
+    }
 
     for (y, row) in intervals.iter().enumerate() {
-        if row.len() < 1 {
-            continue;
+        if let Some(span) = find_misplaced_span(row, width) {
+            return Err(Error::IntervalOutOfBounds {
+                row: y as u32,
+                end: span.end,
+                width,
+            });
         }
-        let mut row_iter = row.iter();
+    }
+    Ok(())
+}
 
-        let mut start = row_iter.next().unwrap();
+/// Checks every point of every line lies within the image, no pixel is visited twice
+/// and the spans stay within the points
+fn validate_line_intervals(intervals: &[interval::Line], width: u32, height: u32) -> Result<()> {
+    let mut visited = vec![false; width as usize * height as usize];
 
-        for pixel_pos in row_iter {
-            let points = math::points_between(&start, &pixel_pos);
+    for (i, line) in intervals.iter().enumerate() {
+        for point in &line.points {
+            if point.0 >= width || point.1 >= height {
+                return Err(Error::LinePointOutOfBounds {
+                    line: i as u32,
+                    point: *point,
+                });
+            }
 
-            let mut pixels_at_points: Vec<Rgb<u8>> = points
-                .iter()
-                .map(|p| image.get_pixel(p.0, p.1).clone())
-                .collect();
+            let index = point.1 as usize * width as usize + point.0 as usize;
 
-            if randomness > 0f32 && rand::random::<f32>() * 100f32 < randomness {
-                points.iter().zip(pixels_at_points).for_each(|(p, pix)| {
-                    output.put_pixel(p.0.clone(), p.1.clone(), pix);
-                });
-            } else {
-                pixels_at_points.sort_by(sorting::get_sort_func(sort_method));
-                points.iter().zip(pixels_at_points).for_each(|(p, pix)| {
-                    output.put_pixel(p.0.clone(), p.1.clone(), pix);
+            if visited[index] {
+                return Err(Error::LinePointRevisited {
+                    line: i as u32,
+                    point: *point,
                 });
             }
-            start = pixel_pos;
+            visited[index] = true;
+        }
+
+        if let Some(span) = find_misplaced_span(&line.spans, line.points.len() as u32) {
+            return Err(Error::LineSpanOutOfBounds {
+                line: i as u32,
+                end: span.end,
+                len: line.points.len() as u32,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Finds the first span that runs backwards, overlaps the span before it or ends after `len`
+fn find_misplaced_span(spans: &[interval::Span], len: u32) -> Option<&interval::Span> {
+    let mut last_end = 0;
+
+    spans.iter().find(|span| {
+        let misplaced = span.start < last_end || span.end < span.start || span.end > len;
+        last_end = span.end;
+        misplaced
+    })
+}
+
+/// Sorts the spans of every line, the mask is passed separately so it can be turned along with the image
+///
+/// No pixel may be on more than one line. Pixels that are on no sorted span
+/// or that the mask leaves out keep their value from the source image
+pub fn get_sorted_image_raw2<P>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    mask_data: Option<&Mask>,
+    intervals: &[interval::Line],
+    sorter: &PixelSorter,
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>>
+where
+    P: Pixel + Send + Sync,
+    P::Subpixel: Send + Sync,
+{
+    let PixelSorter {
+        randomness,
+        sort_method,
+        alpha_mode,
+        order,
+        mask_mode,
+        seed,
+        progress,
+        cancel,
+        ..
+    } = sorter;
+
+    let (randomness, seed) = (*randomness, *seed);
+    let (progress, cancel) = (progress.as_ref(), cancel.as_ref());
+
+    let (width, height) = image.dimensions();
+
+    validate_randomness(randomness)?;
+    validate_line_intervals(intervals, width, height)?;
+
+    if let Some(mask) = mask_data {
+        validate_mask(mask, width, height)?;
+    }
+
+    let mut output = image.clone();
+
+    let lines_done = AtomicU32::new(0);
+
+    let sorted_lines = parallel::map_collect(intervals, |i, line| {
+        let mut sorted: Vec<((u32, u32), P)> = Vec::new();
+
+        if is_cancelled(cancel) {
+            return sorted;
+        }
+
+        let mut rng = rng::row_rng(seed, rng::RngPurpose::SkipSort, i as u64);
+
+        let weight = |i: u32| {
+            let p = line.points[i as usize];
+            mask_data.map_or(1_f32, |mask| mask.weight(p.0, p.1))
+        };
+        let spans = spans_to_sort(&line.spans, *mask_mode, |i| weight(i) > 0_f32);
+
+        for (n, span) in spans.iter().enumerate() {
+            let points = &line.points[span.start as usize..span.end as usize];
+
+            let mut pixels_at_points: Vec<P> =
+                points.iter().map(|p| *image.get_pixel(p.0, p.1)).collect();
+
+            if randomness <= 0f32 || rng.gen::<f32>() * 100f32 >= randomness {
+                sorting::sort_interval(
+                    &mut pixels_at_points,
+                    sort_method.as_ref(),
+                    alpha_mode,
+                    order.is_descending(i as u32, n as u32),
+                );
+            }
 
-            let progress = ((y + 1) as f32 / height as f32 * 100f32) as u32;
+            // masked out points already hold their source value
+            for (i, pix) in (span.start..span.end).zip(pixels_at_points) {
+                let (p, weight) = (line.points[i as usize], weight(i));
 
-            if progress % 10 == 0 && progress != last_progress {
-                println!("Sort progress: {}%", progress);
+                if weight > 0_f32 {
+                    sorted.push((p, blend(image.get_pixel(p.0, p.1), &pix, weight)));
+                }
             }
-            last_progress = progress;
         }
+
+        report_progress(progress, &lines_done, intervals.len() as u32);
+
+        sorted
+    });
+
+    if is_cancelled(cancel) {
+        return Err(Error::Cancelled);
     }
-    output
+
+    for (p, pix) in sorted_lines.into_iter().flatten() {
+        output.put_pixel(p.0, p.1, pix);
+    }
+
+    Ok(output)
 }
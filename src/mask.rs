@@ -0,0 +1,194 @@
+use image::imageops;
+use image::DynamicImage;
+use image::GenericImageView;
+use image::GrayImage;
+use image::Luma;
+use image::Rgb;
+
+use crate::color;
+use crate::color::oklab;
+use crate::sorting::SortKey;
+
+/// Which pixels of an image get sorted and how strongly, stored as one gray level per pixel
+///
+/// 0 keeps the source pixel, 255 takes the sorted pixel and the levels in between blend the two.
+/// Pixels above 0 take part in the sort
+#[derive(Clone)]
+pub struct Mask(GrayImage);
+
+impl Mask {
+    /// The threshold masks have always used, mid gray at 128 is just below it and stays out
+    pub const DEFAULT_THRESHOLD: f32 = 128_f32 / 255_f32;
+
+    /// A mask that lets every pixel through
+    pub fn new(width: u32, height: u32) -> Mask {
+        Mask(GrayImage::from_pixel(width, height, Luma([u8::MAX])))
+    }
+
+    /// A mask that lets the pixels through where the function returns true
+    pub fn from_fn(width: u32, height: u32, f: impl Fn(u32, u32) -> bool) -> Mask {
+        Mask::from_weight_fn(width, height, |x, y| if f(x, y) { 1_f32 } else { 0_f32 })
+    }
+
+    /// A mask with the weight the function returns for every pixel, clamped to 0.0-1.0
+    pub fn from_weight_fn(width: u32, height: u32, f: impl Fn(u32, u32) -> f32) -> Mask {
+        Mask(GrayImage::from_fn(width, height, |x, y| {
+            let weight = f(x, y);
+            let weight = if weight.is_nan() {
+                0_f32
+            } else {
+                weight.clamp(0_f32, 1_f32)
+            };
+
+            Luma([(weight * u8::MAX as f32).round() as u8])
+        }))
+    }
+
+    /// Uses the gray levels of the image as they are
+    pub fn from_gray_image(image: GrayImage) -> Mask {
+        Mask(image)
+    }
+
+    /// A soft mask from the average of the red, green and blue channels of every pixel,
+    /// inverting lets the dark pixels through instead
+    pub fn from_image(image: &DynamicImage, invert: bool) -> Mask {
+        let (width, height) = image.dimensions();
+
+        Mask::from_weight_fn(width, height, |x, y| {
+            let level = average_level(image, x, y);
+            if invert {
+                1_f32 - level
+            } else {
+                level
+            }
+        })
+    }
+
+    /// A hard mask of the pixels whose average of the red, green and blue channels is above
+    /// the threshold in 0.0-1.0, inverting takes the pixels at or below it instead
+    pub fn from_threshold(image: &DynamicImage, threshold: f32, invert: bool) -> Mask {
+        let (width, height) = image.dimensions();
+
+        Mask::from_fn(width, height, |x, y| {
+            (average_level(image, x, y) > threshold) != invert
+        })
+    }
+
+    /// A hard mask of the pixels whose measure is within `lower..=upper`,
+    /// for example `SortMethod::Luma` for a luminance range
+    pub fn from_range<K: SortKey>(
+        image: &DynamicImage,
+        measure: &K,
+        lower: f32,
+        upper: f32,
+    ) -> Mask {
+        let (width, height) = image.dimensions();
+
+        Mask::from_fn(width, height, |x, y| {
+            let level = measure.key(&color::to_rgba_f32(&image.get_pixel(x, y)));
+            (lower..=upper).contains(&level)
+        })
+    }
+
+    /// A hard mask of the pixels close to the color in OKLCH, the hue may be up to
+    /// `hue_tolerance` degrees off and the chroma up to `chroma_tolerance`.
+    /// The hue of a gray color means nothing, so gray colors only key on chroma
+    pub fn from_color_key(
+        image: &DynamicImage,
+        color: Rgb<u8>,
+        hue_tolerance: f32,
+        chroma_tolerance: f32,
+    ) -> Mask {
+        let (width, height) = image.dimensions();
+
+        let [r, g, b] = color.0;
+        let key_chroma = oklab::rgb_get_chroma(&r, &g, &b);
+        let key_hue = oklab::rgb_get_hue(&r, &g, &b);
+
+        Mask::from_fn(width, height, |x, y| {
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            let chroma = oklab::rgb_get_chroma(&r, &g, &b);
+
+            if (chroma - key_chroma).abs() > chroma_tolerance {
+                return false;
+            }
+            if key_chroma < color::GRAY_CHROMA {
+                return true;
+            }
+
+            let hue_distance = (oklab::rgb_get_hue(&r, &g, &b) - key_hue).rem_euclid(360_f32);
+            hue_distance.min(360_f32 - hue_distance) <= hue_tolerance
+        })
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.0.dimensions()
+    }
+
+    /// How much of the sorted pixel is used, from 0.0 to 1.0
+    pub fn weight(&self, x: u32, y: u32) -> f32 {
+        self.0.get_pixel(x, y)[0] as f32 / u8::MAX as f32
+    }
+
+    /// Whether the pixel takes part in the sort
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.0.get_pixel(x, y)[0] > 0
+    }
+
+    /// Swaps the pixels that are let through with the ones that are kept
+    pub fn invert(&self) -> Mask {
+        let mut inverted = self.0.clone();
+        imageops::invert(&mut inverted);
+
+        Mask(inverted)
+    }
+
+    /// Grows the pixels that are let through by `radius` pixels in every direction
+    pub fn dilate(&self, radius: u32) -> Mask {
+        self.filter_square(radius, u8::max)
+    }
+
+    /// Shrinks the pixels that are let through by `radius` pixels from every side
+    pub fn erode(&self, radius: u32) -> Mask {
+        self.filter_square(radius, u8::min)
+    }
+
+    /// Combines every level with its neighbours in a square of `2 * radius + 1` pixels,
+    /// one row pass and one column pass, the square is cut off at the borders
+    fn filter_square(&self, radius: u32, pick: fn(u8, u8) -> u8) -> Mask {
+        let (width, height) = self.dimensions();
+
+        let window = |i: u32, len: u32| i.saturating_sub(radius)..(i + radius + 1).min(len);
+
+        let rows = GrayImage::from_fn(width, height, |x, y| {
+            let level = window(x, width)
+                .map(|wx| self.0.get_pixel(wx, y)[0])
+                .reduce(pick);
+            Luma([level.unwrap_or(0)])
+        });
+
+        Mask(GrayImage::from_fn(width, height, |x, y| {
+            let level = window(y, height)
+                .map(|wy| rows.get_pixel(x, wy)[0])
+                .reduce(pick);
+            Luma([level.unwrap_or(0)])
+        }))
+    }
+
+    pub fn as_gray_image(&self) -> &GrayImage {
+        &self.0
+    }
+
+    /// Turns the mask by 90 degrees clockwise, the same way `imageops::rotate90` turns an image
+    pub(crate) fn rotate90(&self) -> Mask {
+        Mask(imageops::rotate90(&self.0))
+    }
+}
+
+/// The average of the red, green and blue channels of the pixel, from 0.0 to 1.0
+fn average_level(image: &DynamicImage, x: u32, y: u32) -> f32 {
+    let pixel = image.get_pixel(x, y);
+    let intensity = pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32;
+
+    intensity as f32 / (u8::MAX as u32 * 3) as f32
+}
@@ -1,13 +1,9 @@
-use std::f64::EPSILON;
-
-use image::{DynamicImage, GenericImageView};
-
 pub fn unit_vector(p1: (f64, f64), p2: (f64, f64)) -> (f64, f64) {
     let dx = p2.0 - p1.0;
     let dy = p2.1 - p1.1;
     let magnitude = (dx * dx + dy * dy).sqrt();
 
-    if magnitude < EPSILON {
+    if magnitude < f64::EPSILON {
         return (0.0, 0.0);
     }
 
@@ -19,7 +15,7 @@ pub fn unit_vector_u32(p1: &(u32, u32), p2: &(u32, u32)) -> (f64, f64) {
     let dy: i64 = p2.1 as i64 - p1.1 as i64;
     let magnitude = ((dx * dx + dy * dy) as f64).sqrt();
 
-    if magnitude < EPSILON {
+    if magnitude < f64::EPSILON {
         return (0.0, 0.0);
     }
 
@@ -32,13 +28,13 @@ pub fn points_between(p1: &(u32, u32), p2: &(u32, u32)) -> Vec<(u32, u32)> {
     let unit = unit_vector_u32(p1, p2);
 
     let delta_dist_x = if unit.0 == 0_f64 {
-        std::f64::MAX
+        f64::MAX
     } else {
         (1_f64 / unit.0).abs()
     };
 
     let delta_dist_y = if unit.1 == 0_f64 {
-        std::f64::MAX
+        f64::MAX
     } else {
         (1_f64 / unit.1).abs()
     };
@@ -64,7 +60,7 @@ pub fn points_between(p1: &(u32, u32), p2: &(u32, u32)) -> Vec<(u32, u32)> {
         side_dist_y = delta_dist_y;
     }
 
-    let mut next_point = p1.clone();
+    let mut next_point = *p1;
     let mut points = Vec::new();
     while next_point != *p2 {
         points.push(next_point);
@@ -100,18 +96,28 @@ pub fn deg_to_rad(degrees: f64) -> f64 {
     degrees * std::f64::consts::PI / 180.0
 }
 
-pub fn to_binary_mask(image: DynamicImage) -> Vec<Vec<bool>> {
-    let (width, height) = image.dimensions();
-    let mut mask = vec![vec![false; height as usize]; width as usize];
+/// Sobel gradient magnitude of a grid of levels stored row by row,
+/// scaled so a hard step from 0.0 to 1.0 gives 1.0. Pixels past the border repeat the edge
+pub fn sobel_magnitude(levels: &[f32], width: u32, height: u32) -> Vec<f32> {
+    let (width, height) = (width as i64, height as i64);
+
+    let at = |x: i64, y: i64| {
+        let (x, y) = (x.clamp(0, width - 1), y.clamp(0, height - 1));
+        levels[(y * width + x) as usize]
+    };
+
+    let mut magnitude = Vec::with_capacity(levels.len());
 
-    for y in (0..height) {
+    for y in 0..height {
         for x in 0..width {
-            let pixel = image.get_pixel(x, y);
-            let intensity = pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32;
-            let is_foreground = intensity > 128 * 3;
-            mask[x as usize][y as usize] = is_foreground;
+            let gx = (at(x + 1, y - 1) + 2_f32 * at(x + 1, y) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2_f32 * at(x - 1, y) + at(x - 1, y + 1));
+            let gy = (at(x - 1, y + 1) + 2_f32 * at(x, y + 1) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2_f32 * at(x, y - 1) + at(x + 1, y - 1));
+
+            magnitude.push(gx.hypot(gy) / 4_f32);
         }
     }
 
-    mask
+    magnitude
 }
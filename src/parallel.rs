@@ -0,0 +1,71 @@
+//! Row and line iteration that runs on the rayon thread pool when the
+//! `parallel` feature is enabled and sequentially otherwise.
+//! Results are always produced in order, so the output does not depend on the thread count
+
+use image::ImageBuffer;
+use image::Pixel;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Calls `f` with the index and channel data of every row of the image
+#[cfg(feature = "parallel")]
+pub(crate) fn for_each_row<P, F>(output: &mut ImageBuffer<P, Vec<P::Subpixel>>, f: F)
+where
+    P: Pixel,
+    P::Subpixel: Send,
+    F: Fn(u32, &mut [P::Subpixel]) + Send + Sync,
+{
+    let row_len = output.width() as usize * P::CHANNEL_COUNT as usize;
+
+    if row_len == 0 {
+        return;
+    }
+
+    output
+        .par_chunks_exact_mut(row_len)
+        .enumerate()
+        .for_each(|(y, row)| f(y as u32, row));
+}
+
+/// Calls `f` with the index and channel data of every row of the image
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn for_each_row<P, F>(output: &mut ImageBuffer<P, Vec<P::Subpixel>>, f: F)
+where
+    P: Pixel,
+    P::Subpixel: Send,
+    F: Fn(u32, &mut [P::Subpixel]) + Send + Sync,
+{
+    let row_len = output.width() as usize * P::CHANNEL_COUNT as usize;
+
+    if row_len == 0 {
+        return;
+    }
+
+    output
+        .chunks_exact_mut(row_len)
+        .enumerate()
+        .for_each(|(y, row)| f(y as u32, row));
+}
+
+/// Maps every item with its index and collects the results in the original order
+#[cfg(feature = "parallel")]
+pub(crate) fn map_collect<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Send + Sync,
+{
+    items.par_iter().enumerate().map(|(i, t)| f(i, t)).collect()
+}
+
+/// Maps every item with its index and collects the results in the original order
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map_collect<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Send + Sync,
+{
+    items.iter().enumerate().map(|(i, t)| f(i, t)).collect()
+}
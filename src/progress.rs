@@ -0,0 +1,46 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Receives updates while an image is being sorted
+///
+/// Rows and lines can finish on several threads at once,
+/// so `report` may be called concurrently and not always with increasing values
+pub trait Progress: Sync {
+    /// Called every time a row or line is done, `done` out of `total` are finished
+    fn report(&self, done: u32, total: u32);
+}
+
+/// Progress reporter that ignores every update
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn report(&self, _done: u32, _total: u32) {}
+}
+
+impl<F: Fn(u32, u32) + Sync> Progress for F {
+    fn report(&self, done: u32, total: u32) {
+        self(done, total)
+    }
+}
+
+/// Flag to stop a running sort from another thread
+///
+/// Clones share the same flag, so a GUI can keep one and hand another to the sort.
+/// The sort checks it before every row or line and returns `Error::Cancelled` once it is set
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
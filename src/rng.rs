@@ -0,0 +1,26 @@
+//! Seeded random number generation.
+//! Every row or line gets its own generator derived from the seed, so the
+//! random choices made for it do not depend on the order or thread it is sorted on
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// What the random numbers are used for, each purpose gets a separate sequence
+/// so adding or removing draws in one never shifts the numbers of another
+#[derive(Clone, Copy)]
+pub(crate) enum RngPurpose {
+    IntervalLength = 1,
+    SkipSort = 2,
+}
+
+/// Creates the generator for a single row or line
+pub(crate) fn row_rng(seed: u64, purpose: RngPurpose, index: u64) -> ChaCha8Rng {
+    let mut key = [0_u8; 32];
+
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    key[8..16].copy_from_slice(&(purpose as u64).to_le_bytes());
+
+    let mut rng = ChaCha8Rng::from_seed(key);
+    rng.set_stream(index);
+    rng
+}
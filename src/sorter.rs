@@ -0,0 +1,263 @@
+use image::imageops;
+use image::ImageBuffer;
+use image::Pixel;
+
+use crate::interval;
+use crate::progress;
+use crate::sorting;
+use crate::Mask;
+use crate::Result;
+
+/// Which way the intervals run through the image
+pub enum Direction {
+    /// Intervals run along the rows
+    Horizontal,
+    /// The image is turned so the intervals run along the columns
+    Vertical,
+}
+
+/// How the pixels the mask leaves out take part in the sort
+#[derive(Clone, Copy)]
+pub enum MaskMode {
+    /// They are sorted along with the rest but then put back in their place
+    Restore,
+    /// They split the intervals so only the pixels the mask lets through are sorted
+    Split,
+}
+
+/// All the settings of a sort, set up with the builder methods and run with `apply`
+///
+/// Unset options keep the same defaults the CLI uses,
+/// the seed defaults to 0 so the same settings always give the same image
+pub struct PixelSorter {
+    pub(crate) interval: Box<dyn interval::IntervalGenerator>,
+    pub(crate) sort_method: Box<dyn sorting::SortKey>,
+    pub(crate) alpha_mode: sorting::AlphaMode,
+    pub(crate) order: sorting::SortOrder,
+    pub(crate) randomness: f32,
+    pub(crate) mask: Option<Mask>,
+    pub(crate) mask_mode: MaskMode,
+    pub(crate) direction: Direction,
+    pub(crate) seed: u64,
+    pub(crate) threads: usize,
+    pub(crate) progress: Box<dyn progress::Progress>,
+    pub(crate) cancel: Option<progress::CancelToken>,
+}
+
+impl Default for PixelSorter {
+    fn default() -> PixelSorter {
+        PixelSorter {
+            interval: Box::new(interval::Random { length: 50 }),
+            sort_method: Box::new(sorting::SortMethod::Brightness),
+            alpha_mode: sorting::AlphaMode::Sort,
+            order: sorting::SortOrder::Ascending,
+            randomness: 0_f32,
+            mask: None,
+            mask_mode: MaskMode::Restore,
+            direction: Direction::Horizontal,
+            seed: 0,
+            threads: 0,
+            progress: Box::new(progress::NoProgress),
+            cancel: None,
+        }
+    }
+}
+
+impl PixelSorter {
+    pub fn new() -> PixelSorter {
+        PixelSorter::default()
+    }
+
+    /// How the image is split into intervals, any `IntervalGenerator` can be used
+    pub fn interval(mut self, interval: impl interval::IntervalGenerator + 'static) -> PixelSorter {
+        self.interval = Box::new(interval);
+        self
+    }
+
+    /// What the pixels of an interval are ordered by, a `SortMethod` or any other `SortKey`
+    pub fn sort_method(mut self, sort_method: impl sorting::SortKey + 'static) -> PixelSorter {
+        self.sort_method = Box::new(sort_method);
+        self
+    }
+
+    /// How fully transparent pixels are treated
+    pub fn alpha_mode(mut self, alpha_mode: sorting::AlphaMode) -> PixelSorter {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Which way the intervals are sorted, ascending unless set
+    pub fn order(mut self, order: sorting::SortOrder) -> PixelSorter {
+        self.order = order;
+        self
+    }
+
+    /// The chance in percent that an interval is left unsorted
+    pub fn randomness(mut self, randomness: f32) -> PixelSorter {
+        self.randomness = randomness;
+        self
+    }
+
+    /// Which pixels are changed and how much of the sorted pixel they take, see `Mask`
+    pub fn mask(mut self, mask: Mask) -> PixelSorter {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Whether masked out pixels are sorted and put back or split the intervals, restore unless set
+    pub fn mask_mode(mut self, mask_mode: MaskMode) -> PixelSorter {
+        self.mask_mode = mask_mode;
+        self
+    }
+
+    pub fn direction(mut self, direction: Direction) -> PixelSorter {
+        self.direction = direction;
+        self
+    }
+
+    /// Seed for every random choice, the same seed always gives the same image
+    pub fn seed(mut self, seed: u64) -> PixelSorter {
+        self.seed = seed;
+        self
+    }
+
+    /// How many threads to sort with, 0 uses the current rayon thread pool.
+    /// Without the `parallel` feature the sort always runs on the calling thread
+    pub fn threads(mut self, threads: usize) -> PixelSorter {
+        self.threads = threads;
+        self
+    }
+
+    pub fn progress(mut self, progress: impl progress::Progress + 'static) -> PixelSorter {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    pub fn cancel_token(mut self, cancel: progress::CancelToken) -> PixelSorter {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Finds the intervals of the image as they would be sorted horizontally
+    pub fn intervals<P: Pixel>(
+        &self,
+        image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    ) -> Result<interval::IntervalType> {
+        self.interval.generate(image, self.seed)
+    }
+
+    /// Finds the intervals and sorts them into a new image
+    pub fn apply<P>(
+        &self,
+        image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    ) -> Result<ImageBuffer<P, Vec<P::Subpixel>>>
+    where
+        P: Pixel + Send + Sync + 'static,
+        P::Subpixel: Send + Sync,
+    {
+        #[cfg(feature = "parallel")]
+        if self.threads > 0 {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(self.threads)
+                .build()
+                .map_err(|e| crate::Error::ThreadPool(e.to_string()))?;
+
+            return pool.install(|| self.apply_in_direction(image));
+        }
+
+        self.apply_in_direction(image)
+    }
+
+    fn apply_in_direction<P>(
+        &self,
+        image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    ) -> Result<ImageBuffer<P, Vec<P::Subpixel>>>
+    where
+        P: Pixel + Send + Sync + 'static,
+        P::Subpixel: Send + Sync,
+    {
+        match self.direction {
+            Direction::Horizontal => self.find_and_sort(image, self.mask.as_ref()),
+            Direction::Vertical => {
+                let (width, height) = image.dimensions();
+
+                // checked before turning so the error reports the sizes the caller passed in
+                if let Some(mask) = &self.mask {
+                    crate::validate_mask(mask, width, height)?;
+                }
+
+                let rotated = imageops::rotate90(image);
+                let rotated_mask = self.mask.as_ref().map(|m| m.rotate90());
+
+                let sorted = self.find_and_sort(&rotated, rotated_mask.as_ref())?;
+
+                Ok(imageops::rotate270(&sorted))
+            }
+        }
+    }
+
+    fn find_and_sort<P>(
+        &self,
+        image: &ImageBuffer<P, Vec<P::Subpixel>>,
+        mask_data: Option<&Mask>,
+    ) -> Result<ImageBuffer<P, Vec<P::Subpixel>>>
+    where
+        P: Pixel + Send + Sync,
+        P::Subpixel: Send + Sync,
+    {
+        match self.intervals(image)? {
+            interval::IntervalType::DynamicLine(i) => {
+                crate::get_sorted_image_raw2(image, mask_data, &i, self)
+            }
+            interval::IntervalType::HorizontalRow(i) => {
+                crate::get_sorted_image_raw(image, mask_data, &i, self)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn test_image() -> RgbImage {
+        RgbImage::from_fn(64, 48, |x, y| {
+            image::Rgb([
+                (x * 37 + y * 11) as u8,
+                (x * 7 + y * 53) as u8,
+                (x ^ y) as u8,
+            ])
+        })
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_result() {
+        let image = test_image();
+        let sort = |threads| {
+            PixelSorter::new()
+                .seed(7)
+                .randomness(30_f32)
+                .threads(threads)
+                .apply(&image)
+                .unwrap()
+        };
+
+        assert_eq!(sort(1), sort(4));
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_image() {
+        let image = test_image();
+        let sort = |seed| {
+            PixelSorter::new()
+                .seed(seed)
+                .randomness(30_f32)
+                .apply(&image)
+                .unwrap()
+        };
+
+        assert_eq!(sort(7), sort(7));
+        assert_ne!(sort(7), sort(8));
+    }
+}
@@ -1,9 +1,18 @@
 use std::cmp::Ordering;
 
-use image::Rgb;
+use image::Pixel;
 
+use crate::color;
+use crate::color::cmyk;
 use crate::color::hsb;
 use crate::color::hsl;
+use crate::color::lab;
+use crate::color::luma;
+use crate::color::oklab;
+
+/// Intervals shorter than this are sorted with a comparison sort, radix sorting
+/// only pays off once the counting passes are cheaper than the comparisons
+const RADIX_SORT_MIN_LEN: usize = 256;
 
 pub enum SortMethod {
     Hue,
@@ -16,94 +25,301 @@ pub enum SortMethod {
     RgbRed,
     RgbGreen,
     RgbBlue,
+    Alpha,
+    CmykCyan,
+    CmykMagenta,
+    CmykYellow,
+    CmykKey,
+    LabLightness,
+    LabChroma,
+    LabHue,
+    OklabLightness,
+    OklabChroma,
+    OklabHue,
+    /// Weighted sum of the channels with the coefficients of a video standard
+    Luma(luma::LumaStandard),
+}
+
+/// Which way the pixels of the intervals are ordered
+pub enum SortOrder {
+    /// Smallest key first
+    Ascending,
+    /// Largest key first
+    Descending,
+    /// Every other interval of a row or line is descending, starting with ascending
+    AlternatingInterval,
+    /// Every other row or line is descending, starting with ascending
+    AlternatingRow,
 }
 
-pub fn get_sort_func(sort: &SortMethod) -> fn(&Rgb<u8>, &Rgb<u8>) -> Ordering {
-    match sort {
-        SortMethod::Hue => sort_by_hue,
-        SortMethod::HsbSaturation => sort_by_saturation_hsb,
-        SortMethod::HslSaturation => sort_by_saturation_hsl,
-        SortMethod::Brightness => sort_by_brightness,
-        SortMethod::Intensity => sort_by_intensity,
-        SortMethod::Minimum => sort_by_minimum,
-        SortMethod::Lightness => sort_by_lightness,
-        SortMethod::RgbRed => sort_by_rgb_red,
-        SortMethod::RgbGreen => sort_by_rgb_green,
-        SortMethod::RgbBlue => sort_by_rgb_blue,
+impl SortOrder {
+    /// Should the given interval of the given row or line be sorted descending
+    pub fn is_descending(&self, row: u32, interval: u32) -> bool {
+        match self {
+            SortOrder::Ascending => false,
+            SortOrder::Descending => true,
+            SortOrder::AlternatingInterval => interval % 2 == 1,
+            SortOrder::AlternatingRow => row % 2 == 1,
+        }
     }
 }
 
+/// Extracts the value pixels are ordered by, smaller keys come first
+///
+/// Implement this or pass a closure over the normalized channels for an order of your own,
+/// `SortMethod` holds the built-in keys
+pub trait SortKey: Sync {
+    /// The key of a pixel, its channels are normalized to 0.0-1.0 in rgba order
+    fn key(&self, rgba: &[f32; 4]) -> f32;
+}
 
-pub fn sort_by_hue(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    let a_hue = hsb::rgb_get_hue(&a[0], &a[1], &a[2]);
-    let b_hue = hsb::rgb_get_hue(&b[0], &b[1], &b[2]);
+impl<F: Fn(&[f32; 4]) -> f32 + Sync> SortKey for F {
+    fn key(&self, rgba: &[f32; 4]) -> f32 {
+        self(rgba)
+    }
+}
+
+impl SortKey for SortMethod {
+    fn key(&self, rgba: &[f32; 4]) -> f32 {
+        let (r, g, b) = (&rgba[0], &rgba[1], &rgba[2]);
 
-    a_hue.partial_cmp(&b_hue).unwrap()
+        match self {
+            SortMethod::Hue => hsb::rgb_f32_get_hue(r, g, b),
+            SortMethod::HsbSaturation => hsb::rgb_f32_get_saturation(r, g, b),
+            SortMethod::HslSaturation => hsl::rgb_f32_get_saturation(r, g, b),
+            SortMethod::Brightness => hsb::rgb_f32_get_brightness(r, g, b),
+            SortMethod::Lightness => hsl::rgb_f32_get_lightness(r, g, b),
+            SortMethod::Intensity => intensity(rgba),
+            SortMethod::Minimum => r.min(*g).min(*b),
+            SortMethod::RgbRed => rgba[0],
+            SortMethod::RgbGreen => rgba[1],
+            SortMethod::RgbBlue => rgba[2],
+            SortMethod::Alpha => rgba[3],
+            SortMethod::CmykCyan => cmyk::rgb_f32_get_cyan(r, g, b),
+            SortMethod::CmykMagenta => cmyk::rgb_f32_get_magenta(r, g, b),
+            SortMethod::CmykYellow => cmyk::rgb_f32_get_yellow(r, g, b),
+            SortMethod::CmykKey => cmyk::rgb_f32_get_key(r, g, b),
+            SortMethod::LabLightness => lab::rgb_f32_get_lightness(r, g, b),
+            SortMethod::LabChroma => lab::rgb_f32_get_chroma(r, g, b),
+            SortMethod::LabHue => lab::rgb_f32_get_hue(r, g, b),
+            SortMethod::OklabLightness => oklab::rgb_f32_get_lightness(r, g, b),
+            SortMethod::OklabChroma => oklab::rgb_f32_get_chroma(r, g, b),
+            SortMethod::OklabHue => oklab::rgb_f32_get_hue(r, g, b),
+            SortMethod::Luma(standard) => luma::rgb_f32_get_luma(r, g, b, standard),
+        }
+    }
 }
 
-pub fn sort_by_saturation_hsb(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    let a_sat = hsb::rgb_get_saturation(&a[0], &a[1], &a[2]);
-    let b_sat = hsb::rgb_get_saturation(&b[0], &b[1], &b[2]);
+/// Sum of the channels counted in 16 bit steps,
+/// rounding keeps pixels with the same 8 or 16 bit channel sum exactly equal
+fn intensity(rgba: &[f32; 4]) -> f32 {
+    let sum = rgba[0] as f64 + rgba[1] as f64 + rgba[2] as f64;
 
-    a_sat.partial_cmp(&b_sat).unwrap()
+    (sum * u16::MAX as f64).round() as f32
 }
-pub fn sort_by_saturation_hsl(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    let a_sat = hsl::rgb_get_saturation(&a[0], &a[1], &a[2]);
-    let b_sat = hsl::rgb_get_saturation(&b[0], &b[1], &b[2]);
 
-    a_sat.partial_cmp(&b_sat).unwrap()
+/// How fully transparent pixels are treated when an interval is sorted
+pub enum AlphaMode {
+    /// Transparent pixels are sorted like any other pixel
+    Sort,
+    /// Transparent pixels break the interval, every run between them is sorted on its own
+    Break,
+    /// Transparent pixels keep their position and the remaining pixels are sorted around them
+    Exclude,
 }
-pub fn sort_by_brightness(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    let a_bri = hsb::rgb_get_brightness(&a[0], &a[1], &a[2]);
-    let b_bri = hsb::rgb_get_brightness(&b[0], &b[1], &b[2]);
 
-    a_bri.partial_cmp(&b_bri).unwrap()
+/// Compares two pixels by their key, in the same order `sort_pixels` puts them
+pub fn compare<P: Pixel, K: SortKey + ?Sized>(sort_key: &K) -> impl Fn(&P, &P) -> Ordering + '_ {
+    move |a, b| {
+        cmp_keys(
+            sort_key.key(&color::to_rgba_f32(a)),
+            sort_key.key(&color::to_rgba_f32(b)),
+        )
+    }
 }
 
-pub fn sort_by_lightness(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    let a_bri = hsl::rgb_get_lightness(&a[0], &a[1], &a[2]);
-    let b_bri = hsl::rgb_get_lightness(&b[0], &b[1], &b[2]);
+pub fn is_transparent<P: Pixel>(pixel: &P) -> bool {
+    color::to_rgba_f32(pixel)[3] <= 0_f32
+}
+
+/// Sorts the pixels of a single interval in place
+pub fn sort_interval<P: Pixel, K: SortKey + ?Sized>(
+    interval: &mut [P],
+    sort_key: &K,
+    alpha_mode: &AlphaMode,
+    descending: bool,
+) {
+    match alpha_mode {
+        AlphaMode::Sort => sort_pixels(interval, sort_key, descending),
+        AlphaMode::Break => {
+            for run in interval.split_mut(is_transparent) {
+                sort_pixels(run, sort_key, descending);
+            }
+        }
+        AlphaMode::Exclude => {
+            let mut opaque: Vec<P> = interval
+                .iter()
+                .filter(|p| !is_transparent(*p))
+                .copied()
+                .collect();
 
-    a_bri.partial_cmp(&b_bri).unwrap()
+            sort_pixels(&mut opaque, sort_key, descending);
+
+            interval
+                .iter_mut()
+                .filter(|p| !is_transparent(*p))
+                .zip(opaque)
+                .for_each(|(slot, pix)| *slot = pix);
+        }
+    }
 }
 
-pub fn sort_by_intensity(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    let a_int = a[0] as u16 + a[1] as u16 + a[2] as u16;
-    let b_int = b[0] as u16 + b[1] as u16 + b[2] as u16;
+/// Stable sorts the pixels by their key, every key is computed exactly once
+///
+/// Long intervals are radix sorted on the bits of the key instead of compared.
+/// Descending flips the key bits, so pixels with equal keys still keep their order
+pub fn sort_pixels<P: Pixel, K: SortKey + ?Sized>(
+    pixels: &mut [P],
+    sort_key: &K,
+    descending: bool,
+) {
+    let mut keyed: Vec<(u32, P)> = pixels
+        .iter()
+        .map(|p| {
+            let bits = ordered_key_bits(sort_key.key(&color::to_rgba_f32(p)));
 
-    a_int.cmp(&b_int)
+            (if descending { !bits } else { bits }, *p)
+        })
+        .collect();
+
+    if keyed.len() < RADIX_SORT_MIN_LEN {
+        keyed.sort_by_key(|k| k.0);
+    } else {
+        radix_sort(&mut keyed);
+    }
+
+    pixels
+        .iter_mut()
+        .zip(keyed)
+        .for_each(|(slot, (_, pix))| *slot = pix);
 }
 
-pub fn sort_by_minimum(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    let mut a_min = a[0];
+/// Compares two keys the same way `sort_pixels` orders them,
+/// NaN keys get a fixed place at either end instead of panicking
+fn cmp_keys(a: f32, b: f32) -> Ordering {
+    ordered_key_bits(a).cmp(&ordered_key_bits(b))
+}
 
-    if a[1] < a_min {
-        a_min = a[1];
+/// Maps a float onto a u32 whose unsigned order matches the float order
+fn ordered_key_bits(key: f32) -> u32 {
+    // adding zero turns -0.0 into 0.0 so both compare equal like they do as floats
+    let bits = (key + 0_f32).to_bits();
+
+    if bits & 0x8000_0000 != 0 {
+        !bits
+    } else {
+        bits | 0x8000_0000
     }
+}
+
+/// Stable LSD radix sort over the key, one byte per pass.
+/// Passes where every key has the same byte are skipped,
+/// so keys coming from 8 bit channels usually need only one or two passes
+fn radix_sort<P: Copy>(items: &mut Vec<(u32, P)>) {
+    let mut buffer = items.clone();
+
+    for shift in (0..32).step_by(8) {
+        let mut counts = [0_usize; 256];
+
+        for (key, _) in items.iter() {
+            counts[((key >> shift) & 0xff) as usize] += 1;
+        }
+
+        if counts.contains(&items.len()) {
+            continue;
+        }
+
+        let mut offsets = [0_usize; 256];
+        let mut total = 0;
+
+        for (offset, count) in offsets.iter_mut().zip(counts) {
+            *offset = total;
+            total += count;
+        }
 
-    if a[2] < a_min {
-        a_min = a[2];
+        for item in items.iter() {
+            let digit = ((item.0 >> shift) & 0xff) as usize;
+            buffer[offsets[digit]] = *item;
+            offsets[digit] += 1;
+        }
+
+        std::mem::swap(items, &mut buffer);
     }
+}
 
-    let mut b_min = b[0];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
 
-    if b[1] < b_min {
-        b_min = b[1];
+    /// Keys with repeats, negatives, both zeros, infinities, a subnormal and NaN,
+    /// the green channel remembers the starting position to check stability
+    fn keyed_pixels(len: usize) -> Vec<Rgba<f32>> {
+        let specials = [
+            -0_f32,
+            0_f32,
+            f32::NAN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            1e-40,
+            -1e30,
+        ];
+
+        (0..len)
+            .map(|i| {
+                let key = if i % 5 == 0 {
+                    specials[(i / 5) % specials.len()]
+                } else {
+                    ((i * 7919) % 23) as f32 - 11.5
+                };
+                Rgba([key, i as f32, 0_f32, 1_f32])
+            })
+            .collect()
     }
 
-    if b[2] < b_min {
-        b_min = b[2];
+    fn bits(pixels: &[Rgba<f32>]) -> Vec<[u32; 4]> {
+        pixels.iter().map(|p| p.0.map(f32::to_bits)).collect()
     }
 
-    a_min.cmp(&b_min)
-}
-pub fn sort_by_rgb_red(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    a[0].cmp(&b[0])
-}
+    #[test]
+    fn sort_pixels_matches_a_comparison_sort() {
+        let key = |rgba: &[f32; 4]| rgba[0];
+        // NaN is not ordered by partial_cmp, sort_pixels puts it after everything else
+        let cmp = |a: &Rgba<f32>, b: &Rgba<f32>| {
+            a[0].partial_cmp(&b[0])
+                .unwrap_or_else(|| a[0].is_nan().cmp(&b[0].is_nan()))
+        };
+
+        for len in [RADIX_SORT_MIN_LEN - 1, RADIX_SORT_MIN_LEN + 1] {
+            for descending in [false, true] {
+                let mut sorted = keyed_pixels(len);
+                sort_pixels(&mut sorted, &key, descending);
 
-pub fn sort_by_rgb_green(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    a[1].cmp(&b[1])
+                let mut expected = keyed_pixels(len);
+                if descending {
+                    expected.sort_by(|a, b| cmp(b, a));
+                } else {
+                    expected.sort_by(cmp);
+                }
+
+                assert_eq!(
+                    bits(&sorted),
+                    bits(&expected),
+                    "len {} descending {}",
+                    len,
+                    descending
+                );
+            }
+        }
+    }
 }
-pub fn sort_by_rgb_blue(a: &Rgb<u8>, b: &Rgb<u8>) -> Ordering {
-    a[2].cmp(&b[2])
-}
\ No newline at end of file